@@ -1,5 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use launcher::backend::*;
+use launcher::config::*;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::sleep;
@@ -17,7 +18,7 @@ fn searching(c: &mut Criterion) {
         ":exec",
         ":p9383 AUHW#*(Y LIHFP#*(YUPOA*U))",
     ];
-    let config = Arc::new(Config::from_file(&CONFIG_PATH));
+    let config = Arc::new(Config::load(None).0);
     let cache = Arc::new(Mutex::new(Cache::init(&config)));
     c.bench_function("running backend with 9 queries multithreaded", |b| {
         b.iter(|| {
@@ -1,8 +1,24 @@
+use crate::bookmarks;
+use crate::clip_history;
+use crate::clipboard::Clipboard;
+use crate::config::{Config, IndexConfig, CONFIG_PATH};
+use crate::dirs::{self, HOME_PATH};
+use crate::procs::{self, Process};
+use crate::projects;
+use crate::recent;
+use crate::shell;
+use crate::snippets;
+use crate::urls;
+use crate::windows;
 use dns_lookup::lookup_host;
 use filemagic::{flags::Flags, FileMagicError, Magic};
 use fuse_rust::Fuse;
 // use regex::Regex;
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ignore::{
+    overrides::{Override, OverrideBuilder},
+    WalkBuilder,
+};
 use rayon::prelude::*;
 use serde_derive::{Deserialize, Serialize};
 use std::{
@@ -15,87 +31,69 @@ use std::{
     io,
     path::Path,
     process::Command,
-    os::unix::process::CommandExt,
-    sync::Arc,
-    thread,
+    os::unix::{fs::PermissionsExt, process::CommandExt},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::SystemTime,
 };
 use url::Url;
 
-// TODO: use config file
-
-lazy_static! {
-    pub static ref HOME_PATH: String = env::var("HOME").unwrap();
-    pub static ref CONFIG_PATH: String = HOME_PATH.to_string() + "/.config/launcher/launcher.toml";
-}
-
-#[derive(Deserialize, Serialize)]
-pub struct Config {
-    app_locations: Vec<String>,
-    editor: String,       // path to binary
-    results_len: usize,   // show how many results
-    fuzzy_engine: String, // 'fuse' or 'skim'. Use skim if fuse is too slow
-}
-
-impl Config {
-    pub fn default() -> Config {
-        Config {
-            app_locations: vec![
-                "/Applications".to_string(),
-                "/System/Applications".to_string(),
-                "/System/Applications/Utilities".to_string(),
-                "/System/Library/CoreServices/Applications".to_string(),
-            ],
-            editor: "hx".to_string(),
-            results_len: 20,
-            fuzzy_engine: "skim".to_string(),
-        }
-    }
-
-    pub fn from_file(path: &str) -> Config {
-        if let Ok(s) = fs::read_to_string(path) {
-            toml::from_str(&s).unwrap_or_else(|_| Config::default())
-        } else {
-            Config::default()
-        }
-    }
-
-    pub fn write_to_file(&self, path: &str) -> Result<(), Box<dyn Error>> {
-        let path = Path::new(path);
-        if let Some(p) = path.parent() {
-            fs::create_dir_all(p)?;
-        };
-        fs::write(path, toml::to_string(self)?.as_bytes())?;
-        Ok(())
-    }
-}
-
 #[derive(Debug, Clone)]
 pub enum LauncherResult {
     Command(String, String), // command description?
     Url(String),             // opens browser
-    App(String),
-    Bin(String),
+    App(String, bool), // running
+    Bin(String, bool), // shadowed by a binary of the same name earlier in `$PATH`
+    Exec(String, Vec<String>), // binary and its arguments
+    Bookmark(String, String),  // title and url
+    Snippet(String, String),   // name and text
+    Clip(String),              // clipboard history entry
+    Process(Process),
+    Project(String, String, bool), // git checkout, its branch and whether it has changes
     File(String),
     // WebSearch(String), // Retrieve google results
 }
 
 impl LauncherResult {
-    pub fn select(&self, config: &Config, magic_cookie: &Magic) -> Result<bool, Box<dyn Error>> {
+    pub fn select(
+        &self,
+        config: &Config,
+        magic_cookie: &Magic,
+        clipboard: &mut Clipboard,
+    ) -> Result<bool, Box<dyn Error>> {
         match self {
             Self::Command(cmd, param) => {
                 return run_command(cmd, param);
             }
-            Self::Url(url) => {
-                exec_process(&format!("open '{}'", url));
+            Self::Url(url) | Self::Bookmark(_, url) => {
+                exec_process(&shell::join(&["open", url]));
+            }
+            Self::Snippet(_, text) => {
+                let text = snippets::expand(text, clipboard, snippets::ask)?;
+                clipboard.copy(&text)?;
             }
-            Self::App(path) => {
-                exec_process(&format!("open '{}'", path));
+            Self::Clip(text) => clipboard.copy(text)?,
+            Self::Process(process) => procs::kill(process.pid, "TERM")?,
+            Self::Project(path, _, _) => {
+                exec_process(&format!("cd {} && {} .", shell::quote(path), config.editor));
+                return Ok(true);
+            }
+            Self::App(path, true) => windows::detect(&config.window_manager).focus(path)?,
+            Self::App(path, false) => {
+                exec_process(&shell::join(&["open", path]));
+            }
+            Self::Bin(path, _) => {
+                exec_process(&shell::quote(path));
+                return Ok(true);
             }
-            Self::Bin(path) => {
-                exec_process(&format!("{}", path));
+            Self::Exec(_, _) => {
+                exec_process(&self.command_line());
                 return Ok(true);
             }
             Self::File(path) => {
+                let _ = recent::record(path);
                 let magic = magic_cookie
                     .file(path)
                     .unwrap_or_else(|_| panic!("failed to check magic of file `{}`", path));
@@ -104,16 +102,21 @@ impl LauncherResult {
                     .iter()
                     .any(|s| magic.to_lowercase().contains(s))
                 {
-                    exec_process(&format!("{} '{}'", config.editor, path));
+                    exec_process(&format!("{} {}", config.editor, shell::quote(path)));
                 } else {
-                    exec_process(&format!("open '{}'", path));
+                    exec_process(&shell::join(&["open", path]));
                 }
             }
         };
-        return Ok(false);
+        Ok(false)
     }
 
-    fn prerun_command(self, cache: &Cache) -> io::Result<Vec<LauncherResult>> {
+    fn prerun_command(
+        self,
+        cache: &Cache,
+        config: &Config,
+        results_len: usize,
+    ) -> io::Result<Vec<LauncherResult>> {
         if let LauncherResult::Command(cmd, param) = &self {
             match cmd.as_str() {
                 "find" => {
@@ -122,38 +125,314 @@ impl LauncherResult {
                 }
                 "config" => {
                     // open config file
-                    Ok(vec![LauncherResult::File(CONFIG_PATH.clone())])
+                    Ok(CONFIG_PATH.iter().cloned().map(LauncherResult::File).collect())
                 }
+                // most recent first, until something is typed
+                "clip" if param.is_empty() => Ok(clip_history::read()
+                    .into_iter()
+                    .take(results_len)
+                    .map(LauncherResult::Clip)
+                    .collect()),
+                // read on each search, never kept in the snapshot
+                "clip" => Ok(Cache::clip_history().search(
+                    param,
+                    &config.fuzzy_engine,
+                    results_len,
+                    Some(FileEntryType::Clip),
+                )),
+                "recent" if param.is_empty() => Ok(cache
+                    .recent_files()
+                    .into_iter()
+                    .take(results_len)
+                    .map(|path| LauncherResult::File(path.to_string()))
+                    .collect()),
+                "recent" => {
+                    let mut recent = Cache::new();
+                    recent.recent = cache.recent.clone();
+                    recent.file_entries = cache
+                        .file_entries
+                        .iter()
+                        .filter(|e| e.file_type == FileEntryType::File)
+                        .filter(|e| cache.recent.contains_key(&e.full_path))
+                        .cloned()
+                        .collect();
+                    Ok(recent.search(param, &config.fuzzy_engine, results_len, None))
+                }
+                "project" => {
+                    let mut results = cache.search(
+                        param,
+                        &config.fuzzy_engine,
+                        results_len,
+                        Some(FileEntryType::Project),
+                    );
+                    describe_projects(&mut results);
+                    Ok(results)
+                }
+                "kill" | "ps" => Ok(search_processes(param, results_len)?
+                    .into_iter()
+                    .map(LauncherResult::Process)
+                    .collect()),
                 _ => Ok(vec![self]),
             }
         } else {
-            return Ok(vec![self]);
+            Ok(vec![self])
+        }
+    }
+
+    // actions listed in the action menu. The first one is the default
+    pub fn actions(&self) -> Vec<Action> {
+        match self {
+            LauncherResult::Command(_, _) => vec![Action::Open],
+            LauncherResult::Snippet(_, _) | LauncherResult::Clip(_) => vec![Action::CopyText],
+            LauncherResult::Process(_) => vec![Action::Kill, Action::ForceKill],
+            LauncherResult::Url(_) | LauncherResult::Bookmark(_, _) => {
+                vec![Action::Open, Action::CopyUrl]
+            }
+            LauncherResult::Bin(_, _) => vec![
+                Action::Open,
+                Action::RunInTerminal,
+                Action::Reveal,
+                Action::CopyPath,
+            ],
+            LauncherResult::Exec(_, _) => vec![
+                Action::Open,
+                Action::CopyCommand,
+                Action::Reveal,
+                Action::CopyPath,
+            ],
+            LauncherResult::App(_, true) => vec![
+                Action::Focus,
+                Action::Quit,
+                Action::NewWindow,
+                Action::Reveal,
+                Action::CopyPath,
+                Action::OpenTerminalHere,
+            ],
+            LauncherResult::Project(_, _, _) => vec![
+                Action::Open,
+                Action::OpenTerminalHere,
+                Action::OpenInFileManager,
+                Action::CopyPath,
+            ],
+            LauncherResult::App(_, false) | LauncherResult::File(_) => vec![
+                Action::Open,
+                Action::Reveal,
+                Action::CopyPath,
+                Action::OpenInEditor,
+                Action::OpenWith(None),
+                Action::OpenTerminalHere,
+                Action::MoveToTrash,
+            ],
+        }
+    }
+
+    pub fn run_action(
+        &self,
+        action: &Action,
+        config: &Config,
+        magic_cookie: &Magic,
+        clipboard: &mut Clipboard,
+    ) -> Result<bool, Box<dyn Error>> {
+        let path = match self {
+            LauncherResult::Command(_, _)
+            | LauncherResult::Snippet(_, _)
+            | LauncherResult::Clip(_) => return self.select(config, magic_cookie, clipboard),
+            LauncherResult::Process(process) => {
+                if *action == Action::ForceKill {
+                    procs::kill(process.pid, "KILL")?;
+                    return Ok(false);
+                }
+                return self.select(config, magic_cookie, clipboard);
+            }
+            LauncherResult::Url(url) | LauncherResult::Bookmark(_, url) => url,
+            LauncherResult::App(path, _)
+            | LauncherResult::Bin(path, _)
+            | LauncherResult::Exec(path, _)
+            | LauncherResult::Project(path, _, _)
+            | LauncherResult::File(path) => path,
+        };
+        // `Open` records through `select`
+        if matches!(self, LauncherResult::File(_))
+            && matches!(action, Action::OpenInEditor | Action::OpenWith(Some(_)))
+        {
+            let _ = recent::record(path);
         }
+        match action {
+            Action::Open
+            | Action::OpenWith(None)
+            | Action::CopyText
+            | Action::Kill
+            | Action::Focus => {
+                return self.select(config, magic_cookie, clipboard)
+            }
+            Action::Reveal => {
+                exec_process(&shell::join(&["open", "-R", path]));
+            }
+            Action::CopyPath | Action::CopyUrl => clipboard.copy(path)?,
+            Action::CopyCommand => clipboard.copy(&self.command_line())?,
+            Action::OpenInEditor => {
+                exec_process(&format!("{} {}", config.editor, shell::quote(path)));
+            }
+            Action::OpenWith(Some(app)) => {
+                exec_process(&shell::join(&["open", "-a", app, path]));
+            }
+            Action::OpenTerminalHere => {
+                let dir = if Path::new(path).is_dir() {
+                    Path::new(path)
+                } else {
+                    Path::new(path).parent().unwrap_or(Path::new("/"))
+                };
+                exec_process(&shell::join(&["open", "-a", "Terminal", &dir.to_string_lossy()]));
+            }
+            Action::MoveToTrash => {
+                exec_process(&trash_command(path));
+            }
+            Action::RunInTerminal => {
+                exec_process(&shell::join(&["open", "-a", "Terminal", path]));
+            }
+            Action::ForceKill => {}
+            Action::OpenInFileManager => {
+                exec_process(&shell::join(&["open", path]));
+            }
+            Action::Quit => windows::detect(&config.window_manager).quit(path)?,
+            Action::NewWindow => windows::detect(&config.window_manager).new_window(path)?,
+        };
+        Ok(false)
     }
 
     pub fn get_string(&self) -> String {
         match self {
             LauncherResult::Command(cmd, param) => format!("Cmd  | :{} {}", cmd, param),
             LauncherResult::Url(url) => format!("Url  | {}", url),
-            LauncherResult::App(app) => format!("App  | {}", app),
-            LauncherResult::Bin(bin) => format!("Bin  | {}", bin),
+            LauncherResult::Bookmark(title, url) => format!("Mark | {} ({})", title, url),
+            LauncherResult::Snippet(name, text) => {
+                format!("Snip | {}: {}", name, text.lines().next().unwrap_or(""))
+            }
+            LauncherResult::Clip(text) => {
+                format!("Clip | {}", text.split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+            LauncherResult::App(app, false) => format!("App  | {}", app),
+            LauncherResult::App(app, true) => format!("App  | {} (running)", app),
+            LauncherResult::Bin(bin, false) => format!("Bin  | {}", bin),
+            LauncherResult::Bin(bin, true) => format!("Bin  | {} (shadowed)", bin),
+            LauncherResult::Exec(_, _) => format!("Run  | {}", self.command_line()),
             LauncherResult::File(file) => format!("File | {}", file),
+            LauncherResult::Project(path, branch, dirty) => {
+                format!("Proj | {} [{}{}]", path, branch, if *dirty { " *" } else { "" })
+            }
+            LauncherResult::Process(p) => format!(
+                "Proc | {} ({}) {:.1}% {}M {}",
+                p.name,
+                p.pid,
+                p.cpu,
+                p.rss / 1024,
+                p.cmdline
+            ),
+        }
+    }
+
+    // what Tab puts in the query. `get_string` without the kind and notes
+    // like `(running)`
+    pub fn completion_text(&self) -> String {
+        match self {
+            LauncherResult::Command(cmd, param) => {
+                format!(":{} {}", cmd, param).trim_end().to_string()
+            }
+            LauncherResult::Url(url) | LauncherResult::Bookmark(_, url) => url.clone(),
+            LauncherResult::Snippet(name, _) => name.clone(),
+            LauncherResult::Clip(text) => text.split_whitespace().collect::<Vec<_>>().join(" "),
+            LauncherResult::Process(p) => p.name.clone(),
+            LauncherResult::App(path, _)
+            | LauncherResult::Bin(path, _)
+            | LauncherResult::Project(path, _, _)
+            | LauncherResult::File(path) => path.clone(),
+            LauncherResult::Exec(_, _) => self.command_line(),
+        }
+    }
+
+    // what `Exec` passes to the shell
+    fn command_line(&self) -> String {
+        match self {
+            LauncherResult::Exec(bin, args) => {
+                shell::join(&[std::slice::from_ref(bin), args].concat())
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Open, // same as `LauncherResult::select`
+    Reveal,
+    CopyPath,
+    OpenInEditor,
+    OpenWith(Option<String>), // path to app. `None` asks the user to pick one
+    OpenTerminalHere,
+    MoveToTrash,
+    CopyUrl,
+    RunInTerminal,
+    CopyCommand, // command line of `LauncherResult::Exec`
+    CopyText,    // expanded snippet
+    Kill,        // SIGTERM
+    ForceKill,   // SIGKILL
+    Focus,       // of a running app
+    Quit,
+    NewWindow,
+    OpenInFileManager, // the directory itself, where `Reveal` selects it in its parent
+}
+
+impl Action {
+    pub fn get_string(&self) -> String {
+        match self {
+            Action::Open => "Open".to_string(),
+            Action::Reveal => "Reveal in Finder".to_string(),
+            Action::CopyPath => "Copy path".to_string(),
+            Action::OpenInEditor => "Open in editor".to_string(),
+            Action::OpenWith(None) => "Open with...".to_string(),
+            Action::OpenWith(Some(app)) => format!("Open with {}", app),
+            Action::OpenTerminalHere => "Open terminal here".to_string(),
+            Action::MoveToTrash => "Move to trash".to_string(),
+            Action::CopyUrl => "Copy URL".to_string(),
+            Action::RunInTerminal => "Run in terminal".to_string(),
+            Action::CopyCommand => "Copy command".to_string(),
+            Action::CopyText => "Copy text".to_string(),
+            Action::Kill => "Kill".to_string(),
+            Action::ForceKill => "Force kill".to_string(),
+            Action::Focus => "Focus".to_string(),
+            Action::Quit => "Quit".to_string(),
+            Action::NewWindow => "New window".to_string(),
+            Action::OpenInFileManager => "Open in file manager".to_string(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FileEntryType {
     App,
     Bin,
     File,
+    Bookmark, // `name` is the title, `full_path` the url
+    Snippet,  // `full_path` is the text
+    Clip,     // `full_path` is the text. Only searched with `:clip`, never in the snapshot
+    Project,  // a git checkout
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FileEntry {
     file_type: FileEntryType,
     full_path: String,
     name: String,
+    #[serde(skip)]
+    shadowed: bool, // see `Cache::from_snapshot`
+}
+
+impl FileEntry {
+    // what the query is matched against
+    fn keys(&self) -> impl Iterator<Item = &str> {
+        let url = (self.file_type == FileEntryType::Bookmark).then_some(self.full_path.as_str());
+        std::iter::once(self.name.as_str()).chain(url)
+    }
 }
 
 impl Hash for FileEntry {
@@ -166,20 +445,106 @@ impl Hash for FileEntry {
 pub struct Cache {
     pub file_entries: HashSet<Arc<FileEntry>>,
     pub search_results: HashMap<String, Arc<Vec<LauncherResult>>>,
+    recent: HashMap<String, usize>, // path of a recent file to its rank, 0 is the newest
+    // new for every cache and when results are cleared. Results searched in
+    // an older generation are dropped
+    pub generation: u64,
 }
 
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
 macro_rules! into_string {
     ($expr:expr) => {
         $expr.to_str().unwrap().to_string()
     };
 }
 
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const SNAPSHOT_VERSION: u32 = 1;
+const CLIP_NAME_LEN: usize = 256;
+// added to the fuzzy score of the newest file, less for older ones
+const RECENT_BONUS: i64 = 32;
+const RECENT_SOURCE: &str = "recent:";
+
+// On-disk copy of the file index, see `dirs::index_file`. Entries are kept
+// per indexed location so each can be checked against the filesystem alone
+#[derive(Serialize, Deserialize, Default)]
+pub struct Snapshot {
+    version: u32,
+    sources: HashMap<String, IndexSource>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct IndexSource {
+    dirs: Vec<(String, Option<SystemTime>)>, // directories read, with their mtime
+    entries: Vec<Arc<FileEntry>>,
+}
+
+impl IndexSource {
+    fn is_fresh(&self) -> bool {
+        self.dirs.iter().all(|(dir, mtime)| modified(dir) == *mtime)
+    }
+}
+
+impl Snapshot {
+    // empty if missing, unreadable or from another version
+    pub fn load() -> Snapshot {
+        dirs::index_file()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|bytes| bincode::deserialize::<Snapshot>(&bytes).ok())
+            .filter(|snapshot| snapshot.version == SNAPSHOT_VERSION)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = match dirs::index_file() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        dirs::ensure_parent(&path)?;
+        // so that a concurrent `load` never reads a partial file. Two saves of
+        // this process may run at once, too
+        static SAVES: AtomicU64 = AtomicU64::new(0);
+        let tmp = format!(
+            "{}.{}.{}",
+            path,
+            std::process::id(),
+            SAVES.fetch_add(1, Ordering::Relaxed)
+        );
+        fs::write(&tmp, bincode::serialize(self)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+// skips broken symlinks and directories too
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
 impl Cache {
     pub fn new() -> Cache {
-        return Cache {
+        Cache {
             file_entries: HashSet::new(),
             search_results: HashMap::new(),
-        };
+            recent: HashMap::new(),
+            generation: GENERATION.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    // results searched before are not added any more
+    pub fn clear_results(&mut self) {
+        self.search_results.clear();
+        self.generation = GENERATION.fetch_add(1, Ordering::Relaxed);
     }
 
     fn parent_entry<P>(location: P) -> FileEntry
@@ -203,52 +568,320 @@ impl Cache {
             file_type: FileEntryType::File,
             full_path,
             name,
+            shadowed: false,
         }
     }
 
-    fn add_dir<T>(&mut self, locations: T, r#type: FileEntryType)
-    where
-        T: IntoIterator,
-        <T as IntoIterator>::Item: AsRef<Path>,
-    {
-        for location in locations {
-            if let Ok(dir) = fs::read_dir(&location) {
-                // Add the director it self. Mark it as `file`
-                {
-                    let entry = Cache::parent_entry(&location);
-                    self.file_entries.insert(Arc::new(entry));
+    // the directory itself and its content
+    fn scan_dir(location: &str, r#type: FileEntryType) -> IndexSource {
+        let mut source = IndexSource {
+            dirs: vec![(location.to_string(), modified(location))],
+            entries: vec![],
+        };
+        if let Ok(dir) = fs::read_dir(location) {
+            // Add the director it self. Mark it as `file`
+            source.entries.push(Arc::new(Cache::parent_entry(location)));
+            // Then the directory content
+            for path in dir {
+                let path = path.unwrap();
+                if r#type == FileEntryType::Bin && !is_executable(&path.path()) {
+                    continue;
                 }
-                // Then the directory content
-                for path in dir {
-                    let path = path.unwrap();
-
-                    let name = into_string!(path.file_name());
-                    self.file_entries.insert(Arc::new(FileEntry {
-                        file_type: r#type,
-                        full_path: into_string!(path.path()),
-                        name,
-                    }));
+
+                let name = into_string!(path.file_name());
+                source.entries.push(Arc::new(FileEntry {
+                    file_type: r#type,
+                    full_path: into_string!(path.path()),
+                    name,
+                    shadowed: false,
+                }));
+            }
+        }
+        source
+    }
+
+    // walk an `[[index]]` root
+    fn scan_index(index: &IndexConfig) -> IndexSource {
+        let r#type = match index.entry_type.as_str() {
+            "app" => FileEntryType::App,
+            "bin" => FileEntryType::Bin,
+            _ => FileEntryType::File,
+        };
+        let mut overrides = OverrideBuilder::new(&index.path);
+        for glob in &index.include {
+            let _ = overrides.add(glob);
+        }
+        for glob in &index.exclude {
+            let _ = overrides.add(&format!("!{}", glob));
+        }
+        let walker = WalkBuilder::new(&index.path)
+            .max_depth(Some(index.max_depth))
+            .hidden(!index.hidden)
+            .ignore(index.gitignore)
+            .git_ignore(index.gitignore)
+            .git_global(index.gitignore)
+            .git_exclude(index.gitignore)
+            .parents(index.gitignore)
+            .require_git(false)
+            .overrides(overrides.build().unwrap_or_else(|_| Override::empty()))
+            .build();
+        let mut source = IndexSource {
+            dirs: vec![(index.path.clone(), modified(&index.path))],
+            entries: vec![],
+        };
+        // skip the root itself and paths that are not UTF-8
+        for entry in walker.flatten().filter(|e| e.depth() > 0) {
+            if let (Some(full_path), Some(name)) =
+                (entry.path().to_str(), entry.file_name().to_str())
+            {
+                if entry.file_type().is_some_and(|t| t.is_dir()) {
+                    source.dirs.push((full_path.to_string(), modified(full_path)));
                 }
+                source.entries.push(Arc::new(FileEntry {
+                    file_type: r#type,
+                    full_path: full_path.to_string(),
+                    name: name.to_string(),
+                    shadowed: false,
+                }));
             }
         }
+        source
+    }
+
+    // a bookmark file. Unreadable ones are skipped
+    fn scan_bookmarks(path: &str) -> IndexSource {
+        IndexSource {
+            dirs: vec![(path.to_string(), modified(path))],
+            entries: bookmarks::read(path)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(title, url)| {
+                    Arc::new(FileEntry {
+                        file_type: FileEntryType::Bookmark,
+                        full_path: url,
+                        name: title,
+                        shadowed: false,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    fn scan_snippets(path: &str) -> IndexSource {
+        IndexSource {
+            dirs: vec![(path.to_string(), modified(path))],
+            entries: snippets::read(path)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, text)| {
+                    Arc::new(FileEntry {
+                        file_type: FileEntryType::Snippet,
+                        full_path: text,
+                        name,
+                        shadowed: false,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    // every directory walked is kept, so a new checkout is found without
+    // walking the tree each time
+    fn scan_projects(root: &str, max_depth: usize) -> IndexSource {
+        let (repos, dirs) = projects::discover(root, max_depth);
+        IndexSource {
+            dirs: dirs
+                .into_iter()
+                .map(|dir| {
+                    let mtime = modified(&dir);
+                    (dir, mtime)
+                })
+                .collect(),
+            entries: repos
+                .into_iter()
+                .map(|repo| {
+                    Arc::new(FileEntry {
+                        file_type: FileEntryType::Project,
+                        name: Path::new(&repo)
+                            .file_name()
+                            .map(|n| n.to_string_lossy().into_owned())
+                            .unwrap_or_default(),
+                        full_path: repo,
+                        shadowed: false,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    fn clip_history() -> Cache {
+        let mut cache = Cache::new();
+        for text in clip_history::read() {
+            cache.file_entries.insert(Arc::new(FileEntry {
+                file_type: FileEntryType::Clip,
+                // long entries are matched by their start
+                name: text.chars().take(CLIP_NAME_LEN).collect(),
+                full_path: text,
+                shadowed: false,
+            }));
+        }
+        cache
+    }
+
+    // newest first
+    fn scan_recent(config: &Config) -> IndexSource {
+        IndexSource {
+            dirs: vec![],
+            entries: recent::list(&config.recent)
+                .into_iter()
+                .map(|path| {
+                    Arc::new(FileEntry {
+                        file_type: FileEntryType::File,
+                        name: Path::new(&path)
+                            .file_name()
+                            .map(|n| n.to_string_lossy().into_owned())
+                            .unwrap_or_default(),
+                        full_path: path,
+                        shadowed: false,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    // recent files, newest first
+    fn recent_files(&self) -> Vec<&str> {
+        let mut files: Vec<(&String, &usize)> = self.recent.iter().collect();
+        files.sort_unstable_by_key(|(_, rank)| **rank);
+        files.into_iter().map(|(path, _)| path.as_str()).collect()
+    }
+
+    // up to `RECENT_BONUS` for the newest file, less the older it is
+    fn recency_bonus(&self, path: &str) -> i64 {
+        match self.recent.get(path) {
+            Some(rank) => RECENT_BONUS * (self.recent.len() - rank) as i64 / self.recent.len() as i64,
+            None => 0,
+        }
     }
 
     pub fn init(config: &Config) -> Cache {
+        Cache::reconcile(config, &Snapshot::default()).0
+    }
+
+    // Every entry of the snapshot, whether still on disk or not. Like the
+    // shell, the first binary of a name in `$PATH` shadows the later ones
+    pub fn from_snapshot(snapshot: &Snapshot, config: &Config) -> Cache {
         let mut cache = Cache::new();
-        cache.add_dir(&config.app_locations, FileEntryType::App);
-        cache.add_dir(
-            &env::var("PATH").unwrap().split(':').collect::<Vec<&str>>(),
-            FileEntryType::Bin,
-        );
-        cache.add_dir(&[HOME_PATH.to_string()], FileEntryType::File);
-        return cache;
+        for (key, source) in &snapshot.sources {
+            if !key.starts_with("bin:") {
+                cache.file_entries.extend(source.entries.iter().cloned());
+            }
+        }
+        if let Some(source) = snapshot.sources.get(RECENT_SOURCE) {
+            for (rank, entry) in source.entries.iter().enumerate() {
+                cache.recent.insert(entry.full_path.clone(), rank);
+            }
+        }
+        let mut dirs = HashSet::new();
+        let mut names = HashSet::new();
+        for location in env::var("PATH").unwrap().split(':') {
+            let source = match snapshot.sources.get(&format!("bin:{}", location)) {
+                Some(source) if dirs.insert(location) => source,
+                _ => continue,
+            };
+            for entry in &source.entries {
+                if entry.file_type != FileEntryType::Bin || names.insert(entry.name.as_str()) {
+                    cache.file_entries.insert(Arc::clone(entry));
+                } else if config.show_shadowed_bins {
+                    cache.file_entries.insert(Arc::new(FileEntry {
+                        shadowed: true,
+                        ..FileEntry::clone(entry)
+                    }));
+                }
+            }
+        }
+        cache
+    }
+
+    // Index everything in `config`, reusing the sources of `old` whose
+    // directories have not been modified since
+    pub fn reconcile(config: &Config, old: &Snapshot) -> (Cache, Snapshot) {
+        let mut snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            sources: HashMap::new(),
+        };
+        let mut add = |key: String, scan: &dyn Fn() -> IndexSource| {
+            let source = match old.sources.get(&key) {
+                Some(source) if source.is_fresh() => source.clone(),
+                _ => scan(),
+            };
+            snapshot.sources.insert(key, source);
+        };
+        for location in &config.app_locations {
+            add(format!("app:{}", location), &|| {
+                Cache::scan_dir(location, FileEntryType::App)
+            });
+        }
+        for location in env::var("PATH").unwrap().split(':') {
+            add(format!("bin:{}", location), &|| {
+                Cache::scan_dir(location, FileEntryType::Bin)
+            });
+        }
+        for location in HOME_PATH.iter() {
+            add(format!("file:{}", location), &|| {
+                Cache::scan_dir(location, FileEntryType::File)
+            });
+        }
+        for index in &config.index {
+            add(format!("index:{:?}", index), &|| Cache::scan_index(index));
+        }
+        for path in dirs::bookmarks_file().iter().chain(&config.bookmarks) {
+            add(format!("bookmarks:{}", path), &|| Cache::scan_bookmarks(path));
+        }
+        for path in dirs::snippets_file().iter() {
+            add(format!("snippets:{}", path), &|| Cache::scan_snippets(path));
+        }
+        for root in &config.projects.roots {
+            add(format!("projects:{}:{}", config.projects.max_depth, root), &|| {
+                Cache::scan_projects(root, config.projects.max_depth)
+            });
+        }
+        // files change without their directory, so this is never reused
+        snapshot
+            .sources
+            .insert(RECENT_SOURCE.to_string(), Cache::scan_recent(config));
+        (Cache::from_snapshot(&snapshot, config), snapshot)
     }
 
     pub fn get_results(&self, query: &str) -> Option<Arc<Vec<LauncherResult>>> {
-        if self.search_results.contains_key(query) {
-            return Some(self.search_results[query].clone());
-        } else {
-            None
+        self.search_results.get(query).cloned()
+    }
+
+    // flag the projects `dirty_projects` found in the results of `query`
+    pub fn mark_dirty(&mut self, query: &str, dirty: &HashSet<String>) {
+        if let Some(results) = self.search_results.get_mut(query) {
+            for r in Arc::make_mut(results).iter_mut() {
+                if let LauncherResult::Project(path, _, is_dirty) = r {
+                    *is_dirty = dirty.contains(path.as_str());
+                }
+            }
+        }
+    }
+
+    // so that `query` is searched again, see `Query::is_live`
+    pub fn remove_results(&mut self, query: &str) {
+        self.search_results.remove(query);
+    }
+
+    // add to the results of `query`, before the web search
+    pub fn add_result(&mut self, query: &str, result: LauncherResult) {
+        if let Some(results) = self.search_results.get_mut(query) {
+            let results = Arc::make_mut(results);
+            let i = match results.last() {
+                Some(LauncherResult::Command(cmd, _)) if cmd == "search" => results.len() - 1,
+                _ => results.len(),
+            };
+            results.insert(i, result);
         }
     }
 
@@ -257,7 +890,18 @@ impl Cache {
             .insert(query.to_string(), Arc::new(results));
     }
 
-    fn search(&self, query: &str, kind: &str, config: &Config) -> Vec<LauncherResult> {
+    // `only` limits the search to one type of entries
+    fn search(
+        &self,
+        query: &str,
+        kind: &str,
+        results_len: usize,
+        only: Option<FileEntryType>,
+    ) -> Vec<LauncherResult> {
+        let wanted = |x: &FileEntry| match only {
+            Some(t) => t == x.file_type,
+            None => x.file_type != FileEntryType::Clip,
+        };
         let mut results: Vec<LauncherResult> = vec![];
 
         let fuzzy_search_results: Vec<Arc<FileEntry>> = match kind {
@@ -266,10 +910,17 @@ impl Cache {
                 let mut fuzzy_search_results = self
                     .file_entries
                     .par_iter()
+                    .filter(|x| wanted(x))
                     .filter_map(|x| {
-                        let (score, indices) = skim.fuzzy_indices(&x.name, query)?;
-                        let coverage = indices.len() * 1024 / x.name.len();
-                        Some((score, coverage, Arc::clone(&x)))
+                        let (score, coverage) = x
+                            .keys()
+                            .filter_map(|key| {
+                                let (score, indices) = skim.fuzzy_indices(key, query)?;
+                                Some((score, indices.len() * 1024 / key.len()))
+                            })
+                            .max()?;
+                        let score = score + self.recency_bonus(&x.full_path);
+                        Some((score, coverage, Arc::clone(x)))
                     })
                     .collect::<Vec<(i64, usize, Arc<FileEntry>)>>();
                 fuzzy_search_results.sort_unstable_by_key(|e| (Reverse(e.0), Reverse(e.1)));
@@ -290,21 +941,27 @@ impl Cache {
                 let mut fuzzy_search_results = self
                     .file_entries
                     .par_iter()
+                    .filter(|x| wanted(x))
                     .filter_map(|x| {
-                        if query.len() <= x.name.len() {
-                            let result = fuse.search(pattern.as_ref(), &x.name)?;
-                            let coverage = (x.name.len() * 512
-                                - result
-                                    .ranges
-                                    .iter()
-                                    .map(|range| range.end - range.start)
-                                    .sum::<usize>()
-                                    * 512)
-                                / x.name.len();
-                            Some(((result.score * 512.0) as i64, coverage, Arc::clone(x)))
-                        } else {
-                            None
-                        }
+                        // lower is better
+                        let (score, coverage) = x
+                            .keys()
+                            .filter(|key| query.len() <= key.len())
+                            .filter_map(|key| {
+                                let result = fuse.search(pattern.as_ref(), key)?;
+                                let coverage = (key.len() * 512
+                                    - result
+                                        .ranges
+                                        .iter()
+                                        .map(|range| range.end - range.start)
+                                        .sum::<usize>()
+                                        * 512)
+                                    / key.len();
+                                Some(((result.score * 512.0) as i64, coverage))
+                            })
+                            .min()?;
+                        let score = score - self.recency_bonus(&x.full_path);
+                        Some((score, coverage, Arc::clone(x)))
                     })
                     .collect::<Vec<(i64, usize, Arc<FileEntry>)>>();
                 fuzzy_search_results.sort_unstable_by_key(|e| (e.0, e.1));
@@ -314,29 +971,51 @@ impl Cache {
                     .collect()
             }
             _ => {
-                panic!("Invalid kind");
+                unreachable!("fuzzy_engine is validated when loading the config");
             }
         };
 
-        let end_index = if fuzzy_search_results.len() < config.results_len {
+        let end_index = if fuzzy_search_results.len() < results_len {
             fuzzy_search_results.len()
         } else {
-            config.results_len
+            results_len
         };
         // FIXME: does it change order?
         results.par_extend(fuzzy_search_results[0..end_index].par_iter().map(
             |r| match r.file_type {
-                FileEntryType::App => LauncherResult::App(r.full_path.clone()),
-                FileEntryType::Bin => LauncherResult::Bin(r.full_path.clone()),
+                FileEntryType::App => LauncherResult::App(r.full_path.clone(), false),
+                FileEntryType::Bin => LauncherResult::Bin(r.full_path.clone(), r.shadowed),
                 FileEntryType::File => LauncherResult::File(r.full_path.clone()),
+                FileEntryType::Bookmark => {
+                    LauncherResult::Bookmark(r.name.clone(), r.full_path.clone())
+                }
+                FileEntryType::Snippet => {
+                    LauncherResult::Snippet(r.name.clone(), r.full_path.clone())
+                }
+                FileEntryType::Clip => LauncherResult::Clip(r.full_path.clone()),
+                // branch and changes are filled in by `describe_projects`
+                // and `Cache::mark_dirty`
+                FileEntryType::Project => {
+                    LauncherResult::Project(r.full_path.clone(), String::new(), false)
+                }
             },
         ));
-        return results;
+        results
     }
 }
 
 pub struct Query(String);
 
+// `:` commands listing what changes while the launcher is open, like the
+// running processes. Their results are never reused
+const LIVE_COMMANDS: [&str; 4] = ["kill", "ps", "clip", "recent"];
+
+impl Default for Query {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Query {
     pub fn new() -> Query {
         Query(String::new())
@@ -346,8 +1025,37 @@ impl Query {
         Query(s.to_string())
     }
 
+    // browse the filesystem instead of searching
+    pub fn is_path(&self) -> bool {
+        ["/", "~/", "./", "../"]
+            .iter()
+            .any(|prefix| self.0.trim_start().starts_with(prefix))
+    }
+
+    // `:kill fire` and the like, searched again each time they are typed
+    pub fn is_live(&self) -> bool {
+        match self.0.trim().strip_prefix(':') {
+            Some(command) => {
+                let command = command.trim();
+                let name = command.split_once(' ').map_or(command, |(name, _)| name);
+                LIVE_COMMANDS.contains(&name)
+            }
+            None => false,
+        }
+    }
+
     // return new Cache entries only
     pub fn parse(&self, config: &Config, cache: Cache) -> io::Result<Cache> {
+        self.parse_with_len(config, cache, config.results_len)
+    }
+
+    // same as `parse`, but shows `results_len` fuzzy search results
+    pub fn parse_with_len(
+        &self,
+        config: &Config,
+        cache: Cache,
+        results_len: usize,
+    ) -> io::Result<Cache> {
         let mut delta = Cache::new();
 
         let query = self.0.trim();
@@ -355,7 +1063,7 @@ impl Query {
             return Ok(delta);
         }
 
-        if cache.get_results(query).is_some() {
+        if cache.get_results(query).is_some() && !self.is_live() {
             return Ok(delta);
         }
         let mut results: Vec<LauncherResult> = vec![];
@@ -368,40 +1076,67 @@ impl Query {
             if let Some((cmd, param)) = stripped.trim().split_once(' ') {
                 results.extend(
                     LauncherResult::Command(cmd.trim().to_string(), param.trim().to_string())
-                        .prerun_command(&cache)?,
+                        .prerun_command(&cache, config, results_len)?,
                 );
             } else {
                 results.extend(
                     LauncherResult::Command(query[1..].trim().to_string(), String::new())
-                        .prerun_command(&cache)?,
+                        .prerun_command(&cache, config, results_len)?,
                 );
             }
             delta.add_results(query, results);
             return Ok(delta);
         }
 
-        // Url
-        let query_clone = query.to_string();
-        let lookup_host_thread = thread::spawn(move || lookup_host(&query_clone));
+        // Path. Children of its directory matching the last component
+        if self.is_path() {
+            delta.add_results(query, Self::browse(config, query, results_len));
+            return Ok(delta);
+        }
 
         // fuzzy search app / bin / opened files
         // only search of query.len() < 15
         if query.len() < 15 {
-            results.extend(cache.search(query, &config.fuzzy_engine, config));
+            results.extend(cache.search(query, &config.fuzzy_engine, results_len, None));
+            mark_running(&mut results, || windows::running(&config.window_manager));
+            describe_projects(&mut results);
+        }
+
+        // Binary with arguments, e.g. `htop -d 5`
+        if let Some((bin, args)) = query.split_once(char::is_whitespace) {
+            if let Some(args) = shell::split(args).filter(|args| !args.is_empty()) {
+                results.extend(
+                    cache
+                        .search(bin, &config.fuzzy_engine, results_len, Some(FileEntryType::Bin))
+                        .into_iter()
+                        .filter_map(|r| match r {
+                            LauncherResult::Bin(path, false) => {
+                                Some(LauncherResult::Exec(path, args.clone()))
+                            }
+                            _ => None,
+                        }),
+                );
+            }
         }
 
-        // File path
-        if Path::new(query).exists() {
-            results.push(LauncherResult::File(query.to_string()));
+        // File path, after `~` and `$VAR` expansion
+        let path = shell::expand(query);
+        if Path::new(&path).exists() {
+            results.push(LauncherResult::File(path.clone()));
         }
         // Relative to $HOME directory
-        let relative = HOME_PATH.clone() + "/" + query;
-        if Path::new(&relative).exists() {
-            results.push(LauncherResult::File(relative));
+        if let Some(home) = HOME_PATH.as_ref().filter(|_| !path.starts_with('/')) {
+            let relative = home.to_string() + "/" + &path;
+            if Path::new(&relative).exists() {
+                results.push(LauncherResult::File(relative));
+            }
         }
 
-        if let Ok(Ok(_)) = lookup_host_thread.join() {
-            results.push(LauncherResult::Url(Self::fix_url(query)));
+        // Url
+        if let Some(url) = urls::expand_shorthand(query, &config.url_shorthands) {
+            results.push(LauncherResult::Url(url));
+        } else if urls::is_url(query) && !names_file(&results, query) {
+            results.push(LauncherResult::Url(urls::normalize(query)));
         }
 
         results.push(LauncherResult::Command(
@@ -410,18 +1145,138 @@ impl Query {
         ));
 
         delta.add_results(query, results);
-        return Ok(delta);
+        Ok(delta)
+    }
+
+    // Url result for hosts that only DNS knows, like `nas.lan`. Blocks, so
+    // it is run after the other results are shown
+    pub fn lookup(&self) -> Option<LauncherResult> {
+        let query = self.0.trim();
+        if self.is_path() || urls::is_url(query) || urls::is_file_name(query) {
+            return None;
+        }
+        // single words are rarely meant as hosts
+        let domain = urls::domain(query).filter(|d| d.contains('.'))?;
+        lookup_host(&domain).ok()?;
+        Some(LauncherResult::Url(urls::normalize(query)))
+    }
+
+    // Directories end with `/`, so that completing one lists its children
+    fn browse(config: &Config, query: &str, results_len: usize) -> Vec<LauncherResult> {
+        let path = shell::expand(query);
+        let (dir, name) = path.split_at(path.rfind('/').map_or(0, |i| i + 1));
+        let mut children = Cache::new();
+        if let Ok(entries) = fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+            for entry in entries.flatten() {
+                let file_name = match entry.file_name().to_str() {
+                    // hidden files only when asked for
+                    Some(n) if !n.starts_with('.') || name.starts_with('.') => n.to_string(),
+                    _ => continue,
+                };
+                // follows symlinks
+                let suffix = if entry.path().is_dir() { "/" } else { "" };
+                children.file_entries.insert(Arc::new(FileEntry {
+                    file_type: FileEntryType::File,
+                    full_path: format!("{}{}{}", dir, file_name, suffix),
+                    name: file_name + suffix,
+                    shadowed: false,
+                }));
+            }
+        }
+        if name.is_empty() {
+            let mut entries = children.file_entries.into_iter().collect::<Vec<_>>();
+            entries.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+            entries
+                .iter()
+                .take(results_len)
+                .map(|e| LauncherResult::File(e.full_path.clone()))
+                .collect()
+        } else {
+            children.search(name, &config.fuzzy_engine, results_len, None)
+        }
     }
 
-    // TODO: more rules
-    fn fix_url(url: &str) -> String {
-        if !url.starts_with("http://") && !url.starts_with("https://") {
-            return String::from("http://") + url;
+}
+
+// Branches are read for every project shown. Changes take git long to
+// find, so they are asked later with `dirty_projects`
+fn describe_projects(results: &mut [LauncherResult]) {
+    for r in results.iter_mut() {
+        if let LauncherResult::Project(path, branch, _) = r {
+            *branch = projects::branch(path).unwrap_or_else(|| "?".to_string());
         }
-        return url.to_string();
     }
 }
 
+// Projects in `results` with changes, asked from git in parallel. Blocks,
+// so it is run after the results are shown
+pub fn dirty_projects(results: &[LauncherResult]) -> HashSet<String> {
+    results
+        .par_iter()
+        .filter_map(|r| match r {
+            LauncherResult::Project(path, _, _) if projects::is_dirty(path) => Some(path.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+// Running apps move first, keeping their order. `running` is called only
+// when apps matched
+fn mark_running<F>(results: &mut [LauncherResult], running: F)
+where
+    F: FnOnce() -> HashSet<String>,
+{
+    if !results.iter().any(|r| matches!(r, LauncherResult::App(_, _))) {
+        return;
+    }
+    let running = running();
+    for r in results.iter_mut() {
+        if let LauncherResult::App(path, is_running) = r {
+            *is_running = running.contains(&windows::app_name(path));
+        }
+    }
+    results.sort_by_key(|r| !matches!(r, LauncherResult::App(_, true)));
+}
+
+// A file, app or binary found is named as typed, like `setup.py` or
+// `Safari.app`, so the query is not meant as a host
+fn names_file(results: &[LauncherResult], query: &str) -> bool {
+    results.iter().any(|r| match r {
+        LauncherResult::File(path)
+        | LauncherResult::App(path, _)
+        | LauncherResult::Bin(path, _) => Path::new(path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.eq_ignore_ascii_case(query)),
+        _ => false,
+    })
+}
+
+// Busiest first, until something is typed. Then by the best match of the
+// name or the command line
+fn search_processes(query: &str, results_len: usize) -> io::Result<Vec<Process>> {
+    let mut processes = procs::source().list()?;
+    processes.retain(|p| p.pid != std::process::id());
+    if query.is_empty() {
+        processes.sort_unstable_by(|a, b| b.cpu.total_cmp(&a.cpu));
+        processes.truncate(results_len);
+        return Ok(processes);
+    }
+    let skim = SkimMatcherV2::default();
+    let mut matches: Vec<(i64, Process)> = processes
+        .into_iter()
+        .filter_map(|p| {
+            let score = [&p.name, &p.cmdline]
+                .iter()
+                .filter_map(|key| skim.fuzzy_match(key, query))
+                .max()?;
+            Some((score, p))
+        })
+        .collect();
+    matches.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(b.1.cpu.total_cmp(&a.1.cpu)));
+    Ok(matches.into_iter().take(results_len).map(|(_, p)| p).collect())
+}
+
 pub fn new_magic_cookie() -> Result<Magic, FileMagicError> {
     let magic_flags = vec![
         Flags::NO_CHECK_APPTYPE,
@@ -436,11 +1291,25 @@ pub fn new_magic_cookie() -> Result<Magic, FileMagicError> {
     }
     let cookie = Magic::open(magic_open_flag)?;
     cookie.load::<String>(&[])?;
-    return Ok(cookie);
+    Ok(cookie)
 }
 
 fn exec_process(s: &str) -> io::Error {
-    return Command::new("bash").arg("-l").arg("-c").arg(s).exec();
+    Command::new("bash").arg("-l").arg("-c").arg(s).exec()
+}
+
+// The path is passed to the script as an argument, never as script text
+fn trash_command(path: &str) -> String {
+    shell::join(&[
+        "osascript",
+        "-e",
+        "on run argv",
+        "-e",
+        "tell application \"Finder\" to delete POSIX file (item 1 of argv)",
+        "-e",
+        "end run",
+        path,
+    ])
 }
 
 fn run_command(cmd: &str, param: &str) -> Result<bool, Box<dyn Error>> {
@@ -448,7 +1317,7 @@ fn run_command(cmd: &str, param: &str) -> Result<bool, Box<dyn Error>> {
         "search" => {
             let mut url = Url::parse("https://www.google.com/search?")?;
             url.query_pairs_mut().append_pair("q", param);
-            exec_process(&format!("open '{}'", url.as_str()));
+            exec_process(&shell::join(&["open", url.as_str()]));
 
             Ok(false)
         }
@@ -466,3 +1335,96 @@ fn run_command(cmd: &str, param: &str) -> Result<bool, Box<dyn Error>> {
         &_ => Ok(false),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::windows::{Stub, WindowManager};
+
+    fn app(path: &str) -> LauncherResult {
+        LauncherResult::App(path.to_string(), false)
+    }
+
+    #[test]
+    fn running_apps_move_first() {
+        let mut results = vec![
+            LauncherResult::File("/Users/me/notes.txt".to_string()),
+            app("/Applications/Notes.app"),
+            app("/Applications/Safari.app"),
+            app("/Applications/Slack.app"),
+        ];
+        let wm = Stub(vec!["Slack".to_string(), "safari".to_string()]);
+        mark_running(&mut results, || wm.running().unwrap());
+        let shown: Vec<String> = results.iter().map(|r| r.get_string()).collect();
+        assert_eq!(
+            shown,
+            [
+                "App  | /Applications/Safari.app (running)",
+                "App  | /Applications/Slack.app (running)",
+                "File | /Users/me/notes.txt",
+                "App  | /Applications/Notes.app",
+            ]
+        );
+    }
+
+    #[test]
+    fn window_manager_is_not_asked_without_apps() {
+        let mut results = vec![LauncherResult::File("/tmp/a".to_string())];
+        mark_running(&mut results, || panic!("asked"));
+    }
+
+    #[test]
+    fn completion_leaves_out_notes() {
+        let running = LauncherResult::App("/Applications/Safari.app".to_string(), true);
+        assert_eq!(running.completion_text(), "/Applications/Safari.app");
+        let shadowed = LauncherResult::Bin("/usr/bin/python3".to_string(), true);
+        assert_eq!(shadowed.completion_text(), "/usr/bin/python3");
+    }
+
+    #[test]
+    fn live_commands() {
+        for query in [":kill", ":ps fire", " :clip  text ", ":recent"] {
+            assert!(Query::from(query).is_live(), "{}", query);
+        }
+        for query in [":project x", ":killall", "kill", ":config"] {
+            assert!(!Query::from(query).is_live(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn projects_are_marked_dirty_later() {
+        let project = |path: &str| LauncherResult::Project(path.to_string(), "main".into(), false);
+        let mut cache = Cache::new();
+        cache.add_results("pro", vec![project("/a"), project("/b")]);
+        cache.mark_dirty("pro", &HashSet::from(["/b".to_string()]));
+        let dirty: Vec<bool> = cache
+            .get_results("pro")
+            .unwrap()
+            .iter()
+            .map(|r| matches!(r, LauncherResult::Project(_, _, true)))
+            .collect();
+        assert_eq!(dirty, [false, true]);
+    }
+
+    #[test]
+    fn trash_takes_the_path_as_an_argument() {
+        let path = "/tmp/a' \"b\"; rm -rf ~";
+        let words = shell::split(&trash_command(path)).unwrap();
+        assert_eq!(words.last().map(String::as_str), Some(path));
+        assert!(words[..words.len() - 1].iter().all(|w| !w.contains("rm -rf")));
+    }
+
+    #[test]
+    fn found_names_are_not_hosts() {
+        let results = vec![LauncherResult::File("/home/me/notes.io".to_string())];
+        assert!(names_file(&results, "notes.io"));
+        assert!(!names_file(&results, "me/notes.io"));
+        assert!(!names_file(&results, "example.io"));
+        let results = vec![app("/Applications/Safari.app"), app("/Applications/Xcode.app")];
+        assert!(urls::is_url("Safari.app"));
+        assert!(names_file(&results, "Safari.app"));
+        assert!(names_file(&results, "xcode.app"));
+        let results = vec![LauncherResult::Bin("/usr/local/bin/deno.land".to_string(), false)];
+        assert!(names_file(&results, "deno.land"));
+    }
+}
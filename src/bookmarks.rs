@@ -0,0 +1,76 @@
+// Bookmarks from local files, as `(title, url)`. The format is told by the
+// file name: Firefox `*.sqlite` (`places.sqlite`), Chromium `Bookmarks` or
+// `*.json`, and `*.toml` with `title = "url"` lines
+use rusqlite::{Connection, OpenFlags};
+use serde_json::Value as Json;
+use std::{error::Error, fs, path::Path};
+use toml::{value::Table, Value};
+
+pub fn read(path: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let name = Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+    if name.ends_with(".sqlite") {
+        firefox(path)
+    } else if name == "Bookmarks" || name.ends_with(".json") {
+        chromium(path)
+    } else if name.ends_with(".toml") {
+        plain(path)
+    } else {
+        Err(format!("unknown bookmark file `{}`", path).into())
+    }
+}
+
+fn firefox(path: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    // Firefox locks the database while it runs. `immutable` reads it anyway
+    let db = Connection::open_with_flags(
+        format!("file:{}?immutable=1", path),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+    )?;
+    let mut statement = db.prepare(
+        "SELECT b.title, p.url FROM moz_bookmarks b JOIN moz_places p ON b.fk = p.id
+         WHERE b.type = 1 AND p.url NOT LIKE 'place:%'",
+    )?;
+    let rows = statement.query_map([], |row| {
+        let url: String = row.get(1)?;
+        let title: Option<String> = row.get(0)?;
+        Ok((title.filter(|t| !t.is_empty()).unwrap_or_else(|| url.clone()), url))
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn chromium(path: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    // urls are leaves of the folder tree under `roots`
+    fn walk(node: &Json, out: &mut Vec<(String, String)>) {
+        match node {
+            Json::Object(o) if o.get("type").and_then(Json::as_str) == Some("url") => {
+                if let (Some(title), Some(url)) = (
+                    o.get("name").and_then(Json::as_str),
+                    o.get("url").and_then(Json::as_str),
+                ) {
+                    out.push((title.to_string(), url.to_string()));
+                }
+            }
+            Json::Object(o) => o.values().for_each(|v| walk(v, out)),
+            Json::Array(a) => a.iter().for_each(|v| walk(v, out)),
+            _ => {}
+        }
+    }
+
+    let json: Json = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let mut bookmarks = vec![];
+    walk(&json["roots"], &mut bookmarks);
+    Ok(bookmarks)
+}
+
+fn plain(path: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let table: Table = toml::from_str(&fs::read_to_string(path)?)?;
+    table
+        .into_iter()
+        .map(|(title, url)| match url {
+            Value::String(url) => Ok((title, url)),
+            _ => Err(format!("{}: `{}` is not a URL string", path, title).into()),
+        })
+        .collect()
+}
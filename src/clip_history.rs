@@ -0,0 +1,84 @@
+// Clipboard history, newest first. `launcher clip watch` records text copied
+// while it runs into `dirs::clip_history_file()`, one JSON string per line
+use crate::clipboard::Clipboard;
+use crate::config::ClipHistoryConfig;
+use crate::dirs;
+use regex::Regex;
+use std::{
+    error::Error,
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    thread,
+    time::Duration,
+};
+
+pub fn read() -> Vec<String> {
+    dirs::clip_history_file()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|s| {
+            s.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Move `text` to the front, keeping at most `size` entries
+pub fn push(text: &str, size: usize) -> Result<(), Box<dyn Error>> {
+    let path = dirs::clip_history_file().ok_or("no state directory")?;
+    let mut entries = read();
+    entries.retain(|e| e != text);
+    entries.insert(0, text.to_string());
+    entries.truncate(size);
+    write(&path, &entries)
+}
+
+pub fn clear() -> Result<(), Box<dyn Error>> {
+    match dirs::clip_history_file() {
+        Some(path) => write(&path, &[]),
+        None => Ok(()),
+    }
+}
+
+// Poll the clipboard until killed. Entries matching `exclude` are not
+// recorded
+pub fn watch(config: &ClipHistoryConfig) -> Result<(), Box<dyn Error>> {
+    let exclude = config.exclude.as_deref().map(Regex::new).transpose()?;
+    let mut clipboard = Clipboard::system();
+    if let Clipboard::Unavailable = clipboard {
+        return Err(clipboard.paste().unwrap_err().into());
+    }
+    // `xclip` fails while nothing was copied
+    let mut last = clipboard.paste().unwrap_or_default();
+    loop {
+        thread::sleep(Duration::from_millis(config.interval_ms));
+        let text = match clipboard.paste() {
+            Ok(text) if text != last => text,
+            _ => continue,
+        };
+        let excluded = exclude.as_ref().is_some_and(|re| re.is_match(&text));
+        if !text.trim().is_empty() && !excluded {
+            push(&text, config.size)?;
+        }
+        last = text;
+    }
+}
+
+// readable by the user only, as it may hold anything that was copied
+fn write(path: &str, entries: &[String]) -> Result<(), Box<dyn Error>> {
+    dirs::ensure_parent(path)?;
+    let tmp = format!("{}.{}", path, std::process::id());
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)?;
+    for entry in entries {
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    }
+    file.flush()?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
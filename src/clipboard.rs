@@ -0,0 +1,130 @@
+// System clipboard through the platform's command line tools
+use crate::shell::in_path;
+use std::{
+    env,
+    io::{self, Read, Write},
+    process::{Command, ExitStatus, Stdio},
+};
+
+pub enum Clipboard {
+    // programs with their arguments, which read from stdin and write to stdout
+    Command {
+        copy: &'static [&'static str],
+        paste: &'static [&'static str],
+    },
+    Memory(String), // for tests
+    Unavailable,    // no tool is installed, copying and pasting fail
+}
+
+impl Clipboard {
+    // `pbcopy` on macOS. On Linux, `wl-copy` on Wayland, else `xclip` or `xsel`
+    pub fn system() -> Clipboard {
+        if cfg!(target_os = "macos") {
+            return Clipboard::Command {
+                copy: &["pbcopy"],
+                paste: &["pbpaste"],
+            };
+        }
+        if env::var_os("WAYLAND_DISPLAY").is_some() && in_path("wl-copy") {
+            return Clipboard::Command {
+                copy: &["wl-copy"],
+                paste: &["wl-paste", "--no-newline"],
+            };
+        }
+        if in_path("xclip") {
+            return Clipboard::Command {
+                copy: &["xclip", "-selection", "clipboard"],
+                paste: &["xclip", "-selection", "clipboard", "-o"],
+            };
+        }
+        if in_path("xsel") {
+            return Clipboard::Command {
+                copy: &["xsel", "--clipboard", "--input"],
+                paste: &["xsel", "--clipboard", "--output"],
+            };
+        }
+        Clipboard::Unavailable
+    }
+
+    pub fn copy(&mut self, text: &str) -> io::Result<()> {
+        match self {
+            Clipboard::Command { copy, .. } => {
+                let mut child = Command::new(copy[0])
+                    .args(&copy[1..])
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .spawn()?;
+                child.stdin.take().unwrap().write_all(text.as_bytes())?;
+                check(copy[0], child.wait()?)
+            }
+            Clipboard::Memory(content) => {
+                *content = text.to_string();
+                Ok(())
+            }
+            Clipboard::Unavailable => Err(unavailable()),
+        }
+    }
+
+    pub fn paste(&mut self) -> io::Result<String> {
+        match self {
+            Clipboard::Command { paste, .. } => {
+                let mut child = Command::new(paste[0])
+                    .args(&paste[1..])
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                let mut text = String::new();
+                child.stdout.take().unwrap().read_to_string(&mut text)?;
+                check(paste[0], child.wait()?)?;
+                Ok(text)
+            }
+            Clipboard::Memory(content) => Ok(content.clone()),
+            Clipboard::Unavailable => Err(unavailable()),
+        }
+    }
+}
+
+fn check(program: &str, status: ExitStatus) -> io::Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} failed: {}", program, status)))
+    }
+}
+
+fn unavailable() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        "no clipboard tool found, install wl-clipboard, xclip or xsel",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_round_trip() {
+        let mut clipboard = Clipboard::Memory(String::new());
+        assert_eq!(clipboard.paste().unwrap(), "");
+        clipboard.copy("a\nb").unwrap();
+        assert_eq!(clipboard.paste().unwrap(), "a\nb");
+    }
+
+    #[test]
+    fn unavailable_fails() {
+        let mut clipboard = Clipboard::Unavailable;
+        assert!(clipboard.copy("a").is_err());
+        assert!(clipboard.paste().is_err());
+    }
+
+    #[test]
+    fn failing_tool_is_an_error() {
+        let mut clipboard = Clipboard::Command {
+            copy: &["false"],
+            paste: &["false"],
+        };
+        assert!(clipboard.copy("a").is_err());
+        assert!(clipboard.paste().is_err());
+    }
+}
@@ -0,0 +1,791 @@
+use crate::dirs;
+use crate::shell;
+use crate::theme::parse_color;
+use ignore::overrides::OverrideBuilder;
+use regex::Regex;
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    fmt, fs,
+    path::Path,
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+use toml::{value::Table, Value};
+
+lazy_static! {
+    pub static ref CONFIG_PATH: Option<String> = dirs::config_file();
+}
+
+pub const SYSTEM_CONFIG_PATH: &str = "/etc/launcher/launcher.toml";
+// `LAUNCHER_RESULTS_LEN=30`. Tables are separated by `__`: `LAUNCHER_THEME__PRESET=light`
+const ENV_PREFIX: &str = "LAUNCHER_";
+const DEFAULT_SOURCE: &str = "default";
+
+// Where each value of the effective config comes from, keyed by dotted field
+// names. Fields not in the map keep their default
+pub type ConfigSources = BTreeMap<String, String>;
+
+// Every field can be left out. Missing fields use `Config::default()`
+#[derive(Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub app_locations: Vec<String>,
+    pub editor: String,           // path to binary
+    pub results_len: usize,       // show how many results
+    pub fuzzy_engine: String,     // 'fuse' or 'skim'. Use skim if fuse is too slow
+    pub results_fit_screen: bool, // show as many results as the terminal fits instead
+    pub mouse: bool,              // disable to select text in the terminal
+    pub show_shadowed_bins: bool, // also list binaries hidden by an earlier `$PATH` entry
+    pub dns_lookup: bool,         // offer hosts like `nas.lan` that resolve. Sends queries to DNS
+    pub url_shorthands: BTreeMap<String, String>, // prefix to URL template. `{}` is the rest
+    pub bookmarks: Vec<String>, // browser bookmark files, besides `bookmarks.toml`
+    pub clip_history: ClipHistoryConfig,
+    pub recent: RecentConfig,
+    pub projects: ProjectsConfig,
+    pub window_manager: String, // 'auto', 'macos', 'wmctrl', 'sway', 'i3', 'processes' or 'none'
+    pub theme: ThemeConfig,
+    pub layout: LayoutConfig,
+    pub index: Vec<IndexConfig>, // extra directories to index, as `[[index]]`
+}
+
+// Colours are names ('red', 'lightblue', ...), '#rrggbb' or 0-255 indices.
+// Unset fields fall back to the preset
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub preset: String, // 'dark' or 'light'
+    pub app: Option<String>,
+    pub bin: Option<String>,
+    pub file: Option<String>,
+    pub url: Option<String>,
+    pub command: Option<String>,
+    pub highlight_fg: Option<String>,
+    pub highlight_bg: Option<String>,
+    pub match_fg: Option<String>,
+    pub border: Option<String>, // 'plain', 'rounded', 'double', 'thick' or 'none'
+    pub highlight_symbol: Option<String>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> ThemeConfig {
+        ThemeConfig {
+            preset: "dark".to_string(),
+            app: None,
+            bin: None,
+            file: None,
+            url: None,
+            command: None,
+            highlight_fg: None,
+            highlight_bg: None,
+            match_fg: None,
+            border: None,
+            highlight_symbol: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub input_position: String, // 'top' or 'bottom'
+    pub compact: bool,          // single line input and no borders
+    pub show_count: bool,       // show number of results next to the input
+}
+
+impl Default for LayoutConfig {
+    fn default() -> LayoutConfig {
+        LayoutConfig {
+            input_position: "top".to_string(),
+            compact: false,
+            show_count: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClipHistoryConfig {
+    pub size: usize,             // entries kept
+    pub exclude: Option<String>, // regex of text never recorded, e.g. tokens
+    pub interval_ms: u64,        // how often the clipboard is checked
+}
+
+impl Default for ClipHistoryConfig {
+    fn default() -> ClipHistoryConfig {
+        ClipHistoryConfig {
+            size: 200,
+            exclude: None,
+            interval_ms: 500,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecentConfig {
+    pub size: usize,       // recent files kept
+    pub xbel: bool,        // read `recently-used.xbel`, written by desktop apps on Linux
+    pub dirs: Vec<String>, // also the files modified last under these
+    pub max_depth: usize,  // of `dirs`
+}
+
+impl Default for RecentConfig {
+    fn default() -> RecentConfig {
+        RecentConfig {
+            size: 100,
+            xbel: true,
+            dirs: vec![],
+            max_depth: 3,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectsConfig {
+    pub roots: Vec<String>, // directories searched for git repositories
+    pub max_depth: usize,   // 1 finds the repositories directly in a root
+}
+
+impl Default for ProjectsConfig {
+    fn default() -> ProjectsConfig {
+        ProjectsConfig {
+            roots: vec![],
+            max_depth: 3,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndexConfig {
+    pub path: String,
+    pub max_depth: usize, // 1 indexes the direct children only
+    #[serde(rename = "type")]
+    pub entry_type: String, // 'file', 'app' or 'bin'
+    pub include: Vec<String>, // globs. Only matching files are indexed if not empty
+    pub exclude: Vec<String>, // globs, e.g. 'node_modules' or 'target'
+    pub hidden: bool,         // index hidden files
+    pub gitignore: bool,      // skip files ignored by .gitignore and .ignore
+}
+
+impl Default for IndexConfig {
+    fn default() -> IndexConfig {
+        IndexConfig {
+            path: String::new(),
+            max_depth: 1,
+            entry_type: "file".to_string(),
+            include: vec![],
+            exclude: vec![],
+            hidden: false,
+            gitignore: true,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            app_locations: vec![
+                "/Applications".to_string(),
+                "/System/Applications".to_string(),
+                "/System/Applications/Utilities".to_string(),
+                "/System/Library/CoreServices/Applications".to_string(),
+            ],
+            editor: "hx".to_string(),
+            results_len: 20,
+            fuzzy_engine: "skim".to_string(),
+            results_fit_screen: false,
+            mouse: true,
+            show_shadowed_bins: false,
+            dns_lookup: false,
+            url_shorthands: BTreeMap::from([(
+                "gh:".to_string(),
+                "https://github.com/{}".to_string(),
+            )]),
+            bookmarks: vec![],
+            clip_history: ClipHistoryConfig::default(),
+            recent: RecentConfig::default(),
+            projects: ProjectsConfig::default(),
+            window_manager: "auto".to_string(),
+            theme: ThemeConfig::default(),
+            layout: LayoutConfig::default(),
+            index: vec![],
+        }
+    }
+}
+
+impl Config {
+    // whether the file index has to be rebuilt for `other`
+    pub fn index_differs(&self, other: &Config) -> bool {
+        self.app_locations != other.app_locations
+            || self.index != other.index
+            || self.show_shadowed_bins != other.show_shadowed_bins
+            || self.bookmarks != other.bookmarks
+            || self.recent != other.recent
+            || self.projects != other.projects
+    }
+
+    // number of fuzzy search results shown when the list is `height` rows high
+    pub fn results_len(&self, height: usize) -> usize {
+        if self.results_fit_screen {
+            height.max(1)
+        } else {
+            self.results_len
+        }
+    }
+
+    // Missing file is not an error. Use `Config::default()` then
+    pub fn from_file(path: &str) -> Result<Config, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(s) => Config::from_str(path, &s),
+            Err(_) => Ok(Config::default()),
+        }
+    }
+
+    pub fn from_str(path: &str, s: &str) -> Result<Config, ConfigError> {
+        let mut config: Config = toml::from_str(s).map_err(|e| {
+            let e = ConfigError::from_toml(path, &e);
+            // toml points unknown fields at the start of their table
+            if e.message.starts_with("unknown field") {
+                e.locate(path, s)
+            } else {
+                e
+            }
+        })?;
+        config.expand_paths();
+        config.validate().map_err(|e| e.locate(path, s))?;
+        Ok(config)
+    }
+
+    // Falls back to `Config::default()` and returns the error to be shown
+    pub fn load(override_path: Option<&str>) -> (Config, Option<ConfigError>) {
+        let (config, _, error) = Config::load_layers(override_path);
+        (config, error)
+    }
+
+    // Merge built-in defaults, `SYSTEM_CONFIG_PATH`, `CONFIG_PATH`,
+    // `override_path` and `LAUNCHER_*` environment variables, later ones
+    // taking precedence. Layers with errors are skipped and the first error
+    // is returned
+    pub fn load_layers(
+        override_path: Option<&str>,
+    ) -> (Config, ConfigSources, Option<ConfigError>) {
+        let mut merged = Table::new();
+        let mut sources = ConfigSources::new();
+        let mut error = None;
+
+        let paths = Config::paths(override_path);
+        for path in &paths {
+            let s = match fs::read_to_string(path) {
+                Ok(s) => s,
+                Err(e) => {
+                    // only the explicitly given file has to exist
+                    if Some(path.as_str()) == override_path {
+                        error.get_or_insert(ConfigError::new(path, &e.to_string()));
+                    }
+                    continue;
+                }
+            };
+            // check each file on its own so errors point into the file
+            let layer = Config::from_str(path, &s).and_then(|_| {
+                toml::from_str::<Table>(&s).map_err(|e| ConfigError::from_toml(path, &e))
+            });
+            match layer {
+                Ok(layer) => merge(&mut merged, &layer, path, "", &mut sources),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        let files = (merged.clone(), sources.clone());
+        for (key, value) in env::vars() {
+            if let Some(name) = key.strip_prefix(ENV_PREFIX) {
+                let mut layer = Table::new();
+                let mut table = &mut layer;
+                let fields = name.to_lowercase();
+                let fields = fields.split("__").collect::<Vec<&str>>();
+                let (last, tables) = fields.split_last().unwrap();
+                for field in tables {
+                    table = match table
+                        .entry(field.to_string())
+                        .or_insert_with(|| Value::Table(Table::new()))
+                    {
+                        Value::Table(t) => t,
+                        _ => unreachable!(),
+                    };
+                }
+                table.insert(last.to_string(), env_value(&value));
+                merge(&mut merged, &layer, &key, "", &mut sources);
+            }
+        }
+
+        match Config::from_table(merged, &sources, &paths) {
+            Ok(config) => (config, sources, error),
+            Err(e) => {
+                error.get_or_insert(e);
+                let (merged, sources) = files;
+                match Config::from_table(merged, &sources, &paths) {
+                    Ok(config) => (config, sources, error),
+                    Err(_) => (Config::default(), ConfigSources::new(), error),
+                }
+            }
+        }
+    }
+
+    // config files in the order they are merged
+    pub fn paths(override_path: Option<&str>) -> Vec<String> {
+        let mut paths = vec![SYSTEM_CONFIG_PATH.to_string()];
+        paths.extend(CONFIG_PATH.clone());
+        paths.extend(override_path.map(|p| p.to_string()));
+        paths
+    }
+
+    // modification times of `paths`, to tell when the config has to be reloaded
+    pub fn modified(paths: &[String]) -> Vec<Option<SystemTime>> {
+        paths
+            .iter()
+            .map(|p| fs::metadata(p).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+
+    // `paths` are the config files. Other sources are the defaults and
+    // environment variables, which have no lines to point to
+    fn from_table(
+        table: Table,
+        sources: &ConfigSources,
+        paths: &[String],
+    ) -> Result<Config, ConfigError> {
+        let source_of = |field: &Option<String>| {
+            field
+                .as_ref()
+                .and_then(|f| sources.get(f))
+                .cloned()
+                .unwrap_or_else(|| DEFAULT_SOURCE.to_string())
+        };
+        let mut config = Value::Table(table).try_into::<Config>().map_err(|e| {
+            let mut e = ConfigError::from_toml("", &e);
+            e.path = source_of(&e.field);
+            e
+        })?;
+        config.expand_paths();
+        config.validate().map_err(|e| {
+            let path = source_of(&e.field);
+            if !paths.contains(&path) {
+                return ConfigError { path, ..e };
+            }
+            match fs::read_to_string(&path) {
+                Ok(s) => e.locate(&path, &s),
+                Err(_) => ConfigError { path, ..e },
+            }
+        })?;
+        Ok(config)
+    }
+
+    // `~` and `$VAR` in path fields
+    fn expand_paths(&mut self) {
+        for path in self.app_locations.iter_mut() {
+            *path = shell::expand(path);
+        }
+        self.editor = shell::expand(&self.editor);
+        for index in self.index.iter_mut() {
+            index.path = shell::expand(&index.path);
+        }
+        for path in self
+            .bookmarks
+            .iter_mut()
+            .chain(self.recent.dirs.iter_mut())
+            .chain(self.projects.roots.iter_mut())
+        {
+            *path = shell::expand(path);
+        }
+    }
+
+    // effective config in TOML, each value commented with where it comes from
+    pub fn dump(&self, sources: &ConfigSources) -> Result<String, Box<dyn Error>> {
+        fn dump_table(
+            out: &mut String,
+            table: &Table,
+            prefix: &str,
+            sources: &ConfigSources,
+        ) {
+            let key_of = |k: &str| {
+                if prefix.is_empty() {
+                    k.to_string()
+                } else {
+                    format!("{}.{}", prefix, k)
+                }
+            };
+            // values have to come before tables
+            for (k, v) in table.iter().filter(|(_, v)| !v.is_table()) {
+                let source = sources
+                    .get(&key_of(k))
+                    .map_or(DEFAULT_SOURCE, |s| s.as_str());
+                out.push_str(&format!("{} = {} # {}\n", toml_key(k), v, source));
+            }
+            for (k, v) in table.iter() {
+                if let Value::Table(t) = v {
+                    out.push_str(&format!("\n[{}]\n", key_of(&toml_key(k))));
+                    dump_table(out, t, &key_of(k), sources);
+                }
+            }
+        }
+
+        let mut out = String::new();
+        if let Value::Table(table) = Value::try_from(self)? {
+            dump_table(&mut out, &table, "", sources);
+        }
+        Ok(out)
+    }
+
+    pub fn write_to_file(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let path = Path::new(path);
+        if let Some(p) = path.parent() {
+            fs::create_dir_all(p)?;
+        };
+        fs::write(path, toml::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    // reject values that are only known to be wrong when they are used
+    fn validate(&self) -> Result<(), ConfigError> {
+        fn one_of(field: &str, value: &str, expected: &[&str]) -> Result<(), ConfigError> {
+            if expected.contains(&value) {
+                return Ok(());
+            }
+            Err(ConfigError {
+                path: String::new(),
+                position: None,
+                field: Some(field.to_string()),
+                message: format!(
+                    "invalid value `{}`, expected one of {}",
+                    value,
+                    quote_all(expected)
+                ),
+                suggestion: closest(value, expected.iter().copied()),
+            })
+        }
+
+        one_of("fuzzy_engine", &self.fuzzy_engine, &["skim", "fuse"])?;
+        if self.results_len == 0 && !self.results_fit_screen {
+            return Err(ConfigError::invalid("results_len", "must be greater than 0"));
+        }
+        one_of(
+            "window_manager",
+            &self.window_manager,
+            &["auto", "macos", "wmctrl", "sway", "i3", "processes", "none"],
+        )?;
+        one_of("theme.preset", &self.theme.preset, &["dark", "light"])?;
+        if let Some(border) = &self.theme.border {
+            one_of(
+                "theme.border",
+                border,
+                &["plain", "rounded", "double", "thick", "none"],
+            )?;
+        }
+        let colors = [
+            ("theme.app", &self.theme.app),
+            ("theme.bin", &self.theme.bin),
+            ("theme.file", &self.theme.file),
+            ("theme.url", &self.theme.url),
+            ("theme.command", &self.theme.command),
+            ("theme.highlight_fg", &self.theme.highlight_fg),
+            ("theme.highlight_bg", &self.theme.highlight_bg),
+            ("theme.match_fg", &self.theme.match_fg),
+        ];
+        for (field, color) in colors {
+            if let Some(color) = color {
+                if parse_color(color).is_none() {
+                    return Err(ConfigError::invalid(
+                        field,
+                        &format!(
+                            "invalid colour `{}`, expected a name, '#rrggbb' or 0-255",
+                            color
+                        ),
+                    ));
+                }
+            }
+        }
+        one_of(
+            "layout.input_position",
+            &self.layout.input_position,
+            &["top", "bottom"],
+        )?;
+        if let Some(Err(e)) = self.clip_history.exclude.as_deref().map(Regex::new) {
+            return Err(ConfigError::invalid("clip_history.exclude", &e.to_string()));
+        }
+        for (prefix, template) in &self.url_shorthands {
+            if prefix.is_empty() || !template.contains("{}") {
+                return Err(ConfigError::invalid(
+                    "url_shorthands",
+                    &format!("`{}` needs a prefix and a template with `{{}}`", prefix),
+                ));
+            }
+        }
+        for index in &self.index {
+            if index.path.is_empty() {
+                return Err(ConfigError::invalid("index.path", "must not be empty"));
+            }
+            one_of("index.type", &index.entry_type, &["file", "app", "bin"])?;
+            let mut overrides = OverrideBuilder::new("/");
+            for (field, globs) in [
+                ("index.include", &index.include),
+                ("index.exclude", &index.exclude),
+            ] {
+                for glob in globs {
+                    if let Err(e) = overrides.add(glob) {
+                        return Err(ConfigError::invalid(
+                            field,
+                            &format!("invalid glob `{}`: {}", glob, e),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Config shared between threads. It is replaced as a whole when reloaded, so
+// each reader sees one consistent version
+pub struct LiveConfig(RwLock<Arc<Config>>);
+
+impl LiveConfig {
+    pub fn new(config: Config) -> LiveConfig {
+        LiveConfig(RwLock::new(Arc::new(config)))
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        Arc::clone(&self.0.read().unwrap())
+    }
+
+    pub fn set(&self, config: Config) {
+        *self.0.write().unwrap() = Arc::new(config);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    pub path: String,
+    pub position: Option<(usize, usize)>, // line and column, starting from 1
+    pub field: Option<String>,            // dotted path such as `theme.border`
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+impl ConfigError {
+    fn new(path: &str, message: &str) -> ConfigError {
+        ConfigError {
+            path: path.to_string(),
+            position: None,
+            field: None,
+            message: message.to_string(),
+            suggestion: None,
+        }
+    }
+
+    fn invalid(field: &str, message: &str) -> ConfigError {
+        ConfigError {
+            path: String::new(),
+            position: None,
+            field: Some(field.to_string()),
+            message: message.to_string(),
+            suggestion: None,
+        }
+    }
+
+    fn from_toml(path: &str, e: &toml::de::Error) -> ConfigError {
+        // toml appends the position to the message. Keep it structured instead
+        let message = e.to_string();
+        let message = match message.rsplit_once(" at line ") {
+            Some((message, _)) => message.to_string(),
+            None => message,
+        };
+        let mut error = ConfigError {
+            path: path.to_string(),
+            position: e.line_col().map(|(line, col)| (line + 1, col + 1)),
+            field: None,
+            message,
+            suggestion: None,
+        };
+
+        // nested fields end with "for key `table`"
+        let (message, table) = match error.message.rsplit_once(" for key `") {
+            Some((message, table)) => (message.to_string(), table.trim_end_matches('`')),
+            None => (error.message.clone(), ""),
+        };
+        let with_table = |field: &str| {
+            if table.is_empty() || table == field {
+                field.to_string()
+            } else {
+                format!("{}.{}", table, field)
+            }
+        };
+        if let Some(rest) = message.strip_prefix("unknown field ") {
+            // unknown field `edtor`, expected one of `app_locations`, `editor`, ...
+            let names = rest.split('`').skip(1).step_by(2).collect::<Vec<&str>>();
+            if let Some((field, expected)) = names.split_first() {
+                error.suggestion = closest(field, expected.iter().copied());
+                error.field = Some(with_table(field));
+            }
+            error.message = message;
+        } else if let Some(rest) = message.strip_prefix("missing field `") {
+            error.field = rest.split('`').next().map(with_table);
+        } else if !table.is_empty() {
+            error.field = Some(table.to_string());
+        }
+        error
+    }
+
+    // find where the field is set in `s`. Fields without a table are looked
+    // up in the root table first, then in any table
+    fn locate(mut self, path: &str, s: &str) -> ConfigError {
+        self.path = path.to_string();
+        let field = match &self.field {
+            Some(field) => field,
+            None => return self,
+        };
+        let (table, key) = match field.rsplit_once('.') {
+            Some((table, key)) => (Some(table), key),
+            None => (None, field.as_str()),
+        };
+        let find = |any_table: bool| {
+            let mut current_table = None;
+            for (i, line) in s.lines().enumerate() {
+                let trimmed = line.trim();
+                if trimmed.starts_with('[') {
+                    current_table =
+                        Some(trimmed.trim_matches(|c| c == '[' || c == ']').trim());
+                    continue;
+                }
+                let matches_key = trimmed
+                    .split_once('=')
+                    .is_some_and(|(k, _)| k.trim() == key);
+                if matches_key && (any_table || current_table == table) {
+                    let column = line.len() - line.trim_start().len();
+                    return Some((i + 1, column + 1));
+                }
+            }
+            None
+        };
+        let position = match table {
+            Some(_) => find(false),
+            None => find(false).or_else(|| find(true)),
+        };
+        if position.is_some() {
+            self.position = position;
+        }
+        self
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some((line, column)) = self.position {
+            write!(f, ":{}:{}", line, column)?;
+        }
+        write!(f, ": ")?;
+        if let Some(field) = &self.field {
+            if !self.message.contains(&format!("`{}`", field)) {
+                write!(f, "`{}`: ", field)?;
+            }
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean `{}`?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConfigError {}
+
+// merge `layer` into `base`, recording `source` for every value in `layer`
+fn merge(
+    base: &mut Table,
+    layer: &Table,
+    source: &str,
+    prefix: &str,
+    sources: &mut ConfigSources,
+) {
+    for (k, v) in layer {
+        let key = if prefix.is_empty() {
+            k.clone()
+        } else {
+            format!("{}.{}", prefix, k)
+        };
+        if let Value::Table(t) = v {
+            let entry = base
+                .entry(k.clone())
+                .or_insert_with(|| Value::Table(Table::new()));
+            if let Value::Table(b) = entry {
+                merge(b, t, source, &key, sources);
+                continue;
+            }
+        }
+        base.insert(k.clone(), v.clone());
+        sources.insert(key, source.to_string());
+    }
+}
+
+// environment variables are TOML values, or strings if they do not parse
+fn env_value(s: &str) -> Value {
+    toml::from_str::<Table>(&format!("v = {}", s))
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| Value::String(s.to_string()))
+}
+
+// quoted unless it is a bare key
+fn toml_key(k: &str) -> String {
+    if !k.is_empty() && k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        k.to_string()
+    } else {
+        Value::String(k.to_string()).to_string()
+    }
+}
+
+fn quote_all(values: &[&str]) -> String {
+    values
+        .iter()
+        .map(|v| format!("`{}`", v))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// closest candidate by edit distance, if it is close enough to be a typo
+fn closest<'a, I>(s: &str, candidates: I) -> Option<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    candidates
+        .into_iter()
+        .map(|c| (edit_distance(s, c), c))
+        .filter(|(d, c)| *d <= (c.len() / 3).max(1))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c.to_string())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut row = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(row[j + 1])
+            };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,90 @@
+// Files kept between runs. XDG base directories are used when set, otherwise
+// paths under `$HOME` (macOS conventions on macOS). Paths are `None` when
+// neither is set; callers skip whatever needs them.
+use std::{env, fs, io, path::Path};
+
+const APP_NAME: &str = "launcher";
+
+lazy_static! {
+    pub static ref HOME_PATH: Option<String> = non_empty_var("HOME");
+    pub static ref CONFIG_DIR: Option<String> = app_dir("XDG_CONFIG_HOME", ".config", ".config");
+    pub static ref CACHE_DIR: Option<String> = app_dir("XDG_CACHE_HOME", ".cache", "Library/Caches");
+    pub static ref STATE_DIR: Option<String> =
+        app_dir("XDG_STATE_HOME", ".local/state", "Library/Application Support");
+    pub static ref DATA_DIR: Option<String> =
+        app_dir("XDG_DATA_HOME", ".local/share", "Library/Application Support");
+    pub static ref LOG_DIR: Option<String> = if cfg!(target_os = "macos") {
+        HOME_PATH.as_ref().map(|home| format!("{}/Library/Logs/{}", home, APP_NAME))
+    } else {
+        STATE_DIR.as_ref().map(|dir| dir.to_string() + "/logs")
+    };
+}
+
+// launcher.toml
+pub fn config_file() -> Option<String> {
+    CONFIG_DIR.as_ref().map(|dir| dir.to_string() + "/launcher.toml")
+}
+
+// bookmarks kept by hand, as `title = "url"`
+pub fn bookmarks_file() -> Option<String> {
+    CONFIG_DIR.as_ref().map(|dir| dir.to_string() + "/bookmarks.toml")
+}
+
+// named text snippets, see `snippets.rs`
+pub fn snippets_file() -> Option<String> {
+    CONFIG_DIR.as_ref().map(|dir| dir.to_string() + "/snippets.toml")
+}
+
+// queries and launched results
+pub fn history_file() -> Option<String> {
+    STATE_DIR.as_ref().map(|dir| dir.to_string() + "/history")
+}
+
+// shared by desktop apps on Linux, see `recent.rs`. Not ours, so not under
+// `launcher`
+pub fn recently_used_file() -> Option<String> {
+    non_empty_var("XDG_DATA_HOME")
+        .or_else(|| HOME_PATH.as_ref().map(|home| home.to_string() + "/.local/share"))
+        .map(|dir| dir + "/recently-used.xbel")
+}
+
+// recorded by `launcher clip watch`
+pub fn clip_history_file() -> Option<String> {
+    STATE_DIR.as_ref().map(|dir| dir.to_string() + "/clipboard")
+}
+
+// snapshot of the file index. Can be deleted at any time
+pub fn index_file() -> Option<String> {
+    CACHE_DIR.as_ref().map(|dir| dir.to_string() + "/index")
+}
+
+pub fn log_file() -> Option<String> {
+    LOG_DIR.as_ref().map(|dir| dir.to_string() + "/launcher.log")
+}
+
+// create the parent directory of `path`
+pub fn ensure_parent(path: &str) -> io::Result<()> {
+    match Path::new(path).parent() {
+        Some(p) => fs::create_dir_all(p),
+        None => Ok(()),
+    }
+}
+
+fn non_empty_var(var: &str) -> Option<String> {
+    env::var(var).ok().filter(|s| !s.is_empty())
+}
+
+// `$xdg_var/launcher`, or `$HOME/<default>/launcher`
+fn app_dir(xdg_var: &str, default: &str, macos_default: &str) -> Option<String> {
+    if let Some(dir) = non_empty_var(xdg_var) {
+        return Some(format!("{}/{}", dir, APP_NAME));
+    }
+    let default = if cfg!(target_os = "macos") {
+        macos_default
+    } else {
+        default
+    };
+    HOME_PATH
+        .as_ref()
+        .map(|home| format!("{}/{}/{}", home, default, APP_NAME))
+}
@@ -1,31 +1,55 @@
-use crate::backend::LauncherResult;
+use crate::backend::{Action, LauncherResult, Query};
+use crate::clipboard::Clipboard;
+use crate::config::{Config, LayoutConfig};
+use crate::dirs;
+use crate::theme::Theme;
 use backtrace::Backtrace;
 use crossterm::{
     cursor,
-    event::{poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    event::{
+        poll, read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode,
+        KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     execute,
     terminal::{
         disable_raw_mode, enable_raw_mode, Clear, ClearType,
         EnterAlternateScreen, LeaveAlternateScreen,
     },
 };
-use std::time::Duration;
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use std::time::{Duration, Instant};
 use std::{
     error::Error,
-    io::{self, Stdout},
+    fs::OpenOptions,
+    io::{self, Stdout, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc,
+    },
+    thread,
 };
 use tui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Span, Text},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::{Span, Spans, Text},
     widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
     Terminal,
 };
 
+pub enum AppEvent {
+    Terminal(Event),
+    ResultsReady(String), // query of which results are added to the cache
+    ConfigReloaded(Option<String>), // error if the config is not reloaded
+    IndexUpdated,                   // file index reconciled with the filesystem
+}
+
 // TODO: use stateful list
 pub struct App {
     running: bool,
+    listening: Arc<AtomicBool>, // stops the terminal event thread when unset
     terminal: Terminal<CrosstermBackend<Stdout>>,
     query: String,
     prompt: String,
@@ -34,15 +58,38 @@ pub struct App {
     list_state: ListState,
     completion: bool,
     completion_content: Option<String>,
+    selected_content: Option<String>, // selected result as shown in the input field
+    menu_state: Option<ListState>, // `Some` when the action menu is opened
+    menu: Vec<Action>,
+    theme: Theme,
+    layout: LayoutConfig,
+    mouse: bool,
+    matcher: SkimMatcherV2,
+    list_offset: usize, // mirrors the private offset of `list_state`
+    input_area: Rect,
+    list_area: Rect,
+    last_click: Option<(Instant, usize)>, // time and row of the last click
+    status: Option<String>, // shown in the last line, e.g. config errors
+    clipboard: Clipboard,
 }
 
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
 impl App {
-    pub fn init(prompt: &str) -> Result<App, io::Error> {
+    pub fn init(prompt: &str, config: &Config) -> Result<App, io::Error> {
         std::panic::set_hook(Box::new(move |x| {
             cleanup_terminal();
             let bt = Backtrace::new();
             println!("{:?}", bt);
             print!("{:?}", x);
+            if let Some(path) = dirs::log_file() {
+                let _ = dirs::ensure_parent(&path);
+                if let Ok(mut f) =
+                    OpenOptions::new().create(true).append(true).open(&path)
+                {
+                    let _ = writeln!(f, "{:?}\n{:?}", x, bt);
+                }
+            }
         }));
 
         enable_raw_mode()?;
@@ -50,8 +97,9 @@ impl App {
         execute!(stdout, EnterAlternateScreen)?;
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
-        Ok(App {
+        let mut app = App {
             running: true,
+            listening: Arc::new(AtomicBool::new(false)),
             terminal,
             query: String::new(),
             prompt: String::from(prompt),
@@ -60,7 +108,37 @@ impl App {
             list_state: ListState::default(),
             completion: false,
             completion_content: None,
-        })
+            selected_content: None,
+            menu_state: None,
+            menu: vec![],
+            theme: Theme::from_config(&config.theme),
+            layout: config.layout.clone(),
+            mouse: false,
+            matcher: SkimMatcherV2::default(),
+            list_offset: 0,
+            input_area: Rect::default(),
+            list_area: Rect::default(),
+            last_click: None,
+            status: None,
+            clipboard: Clipboard::system(),
+        };
+        app.set_config(config)?;
+        Ok(app)
+    }
+
+    // apply theme, layout and mouse settings of a reloaded config
+    pub fn set_config(&mut self, config: &Config) -> Result<&mut App, io::Error> {
+        self.theme = Theme::from_config(&config.theme);
+        self.layout = config.layout.clone();
+        if config.mouse != self.mouse {
+            if config.mouse {
+                execute!(self.terminal.backend_mut(), EnableMouseCapture)?;
+            } else {
+                execute!(self.terminal.backend_mut(), DisableMouseCapture)?;
+            }
+            self.mouse = config.mouse;
+        }
+        Ok(self)
     }
 
     pub fn update<'a>(
@@ -70,26 +148,69 @@ impl App {
         let list = if self.query.is_empty() { &[] } else { list };
         self.list_len = list.len();
         self.fix_selection();
+        self.selected_content = self.list_state.selected().map(|i| list[i].completion_text());
+        self.menu = match self.list_state.selected() {
+            Some(i) if self.menu_state.is_some() => list[i].actions(),
+            _ => {
+                self.menu_state = None;
+                vec![]
+            }
+        };
         let mut completion_content = None;
         self.terminal.draw(|f| {
+            let compact = self.layout.compact;
+            let input_height =
+                if compact || self.theme.borders == Borders::NONE { 1 } else { 3 };
+            let bottom = self.layout.input_position == "bottom";
+            let constraints = if bottom {
+                [Constraint::Min(0), Constraint::Length(input_height)]
+            } else {
+                [Constraint::Length(input_height), Constraint::Min(0)]
+            };
+            let mut area = f.size();
+            if let Some(status) = &self.status {
+                if area.height > 1 {
+                    area.height -= 1;
+                    let status_area = Rect {
+                        y: area.y + area.height,
+                        height: 1,
+                        ..area
+                    };
+                    let status =
+                        Paragraph::new(Span::styled(status.clone(), self.theme.status));
+                    f.render_widget(status, status_area);
+                }
+            }
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints(
-                    [Constraint::Length(3), Constraint::Min(0)].as_ref(),
-                )
-                .split(f.size());
+                .constraints(constraints.as_ref())
+                .split(area);
+            let (input_chunk, list_chunk) = if bottom {
+                (chunks[1], chunks[0])
+            } else {
+                (chunks[0], chunks[1])
+            };
+
             // input field
-            let block = Block::default().borders(Borders::ALL);
+            let block = make_block(&self.theme, compact);
+            let input_inner = block.inner(input_chunk);
+            self.input_area = input_inner;
+            self.list_area = make_block(&self.theme, compact).inner(list_chunk);
+            self.list_offset = match self.list_state.selected() {
+                Some(i) => {
+                    let height = self.list_area.height as usize;
+                    let offset =
+                        self.list_offset.min(list.len().saturating_sub(1));
+                    if i >= offset + height {
+                        i + 1 - height
+                    } else {
+                        offset.min(i)
+                    }
+                }
+                None => 0,
+            };
             completion_content = if self.completion {
-                Some(
-                    list[self.list_state.selected().unwrap()]
-                        .get_string()
-                        .split_once('|')
-                        .unwrap()
-                        .1
-                        .trim()
-                        .to_string(),
-                )
+                self.selected_content.clone()
             } else {
                 None
             };
@@ -97,29 +218,94 @@ impl App {
                 + &completion_content
                     .clone()
                     .unwrap_or_else(|| self.query.clone());
-            let len = input_field
+            let before_cursor = if completion_content.is_some() {
+                input_field.clone()
+            } else {
+                self.prompt.clone()
+                    + &self.query.chars().take(self.cursor_index).collect::<String>()
+            };
+            let len = before_cursor
                 .chars()
                 .fold(0, |acc, x| acc + 1 + (x.len_utf8() > 1) as usize);
             let input_field = Text::from(Span::from(input_field));
             let paragraph = Paragraph::new(input_field).block(block);
-            f.render_widget(paragraph, chunks[0]);
-            f.set_cursor(1 + len as u16, 1);
+            f.render_widget(paragraph, input_chunk);
+            f.set_cursor(input_inner.x + len as u16, input_inner.y);
+
+            if self.layout.show_count {
+                let count = match self.list_state.selected() {
+                    Some(i) => format!("{}/{}", i + 1, list.len()),
+                    None => format!("0/{}", list.len()),
+                };
+                let count = Paragraph::new(Span::from(count))
+                    .alignment(Alignment::Right);
+                f.render_widget(count, input_inner);
+            }
+
+            // action menu of the selected result
+            if let Some(menu_state) = &mut self.menu_state {
+                let title = list[self.list_state.selected().unwrap()].get_string();
+                let items = self
+                    .menu
+                    .iter()
+                    .map(|a| ListItem::new(Span::from(a.get_string())))
+                    .collect::<Vec<ListItem>>();
+                let items = List::new(items)
+                    .block(make_block(&self.theme, compact).title(title))
+                    .highlight_style(self.theme.highlight)
+                    .highlight_symbol(&self.theme.highlight_symbol);
+                f.render_stateful_widget(items, list_chunk, menu_state);
+                return;
+            }
 
             // search result
             let items = list
                 .iter()
-                .map(|r| ListItem::new(Span::from(r.get_string())))
+                .map(|r| {
+                    let s = r.get_string();
+                    let (kind, content) = s.split_once('|').unwrap_or(("", &s));
+                    let mut spans = vec![Span::styled(
+                        kind.to_string() + "|",
+                        self.theme.kind_style(r),
+                    )];
+                    spans.extend(highlight_matches(
+                        &self.matcher,
+                        content,
+                        &self.query,
+                        self.theme.matched,
+                    ));
+                    ListItem::new(Spans::from(spans))
+                })
                 .collect::<Vec<ListItem>>();
             let items = List::new(items)
-                .block(Block::default().borders(Borders::ALL))
-                .highlight_style(
-                    Style::default()
-                        .fg(Color::Black)
-                        .bg(Color::White)
-                        .add_modifier(Modifier::BOLD),
-                )
-                .highlight_symbol(">> ");
-            f.render_stateful_widget(items, chunks[1], &mut self.list_state);
+                .block(make_block(&self.theme, compact))
+                .highlight_style(self.theme.highlight)
+                .highlight_symbol(&self.theme.highlight_symbol);
+            f.render_stateful_widget(items, list_chunk, &mut self.list_state);
+
+            // scroll position
+            let height = self.list_area.height as usize;
+            if list.len() > height && height > 0 && self.list_area.width > 0 {
+                let thumb_len = (height * height / list.len()).max(1);
+                let thumb_pos =
+                    self.list_offset * (height - thumb_len) / (list.len() - height);
+                let bar = (0..height)
+                    .map(|i| {
+                        let ch = if (thumb_pos..thumb_pos + thumb_len).contains(&i) {
+                            "█"
+                        } else {
+                            "│"
+                        };
+                        Spans::from(Span::styled(ch, self.theme.scrollbar))
+                    })
+                    .collect::<Vec<Spans>>();
+                let area = Rect {
+                    x: self.list_area.x + self.list_area.width - 1,
+                    width: 1,
+                    ..self.list_area
+                };
+                f.render_widget(Paragraph::new(bar), area);
+            }
         })?;
         self.completion_content = completion_content;
         Ok(self)
@@ -133,130 +319,327 @@ impl App {
         }
     }
 
-    pub fn wait_input(
+    // insert at the cursor
+    fn insert_str(&mut self, s: &str) {
+        self.replace_query();
+        let i = self
+            .query
+            .char_indices()
+            .nth(self.cursor_index)
+            .map_or(self.query.len(), |(i, _)| i);
+        self.query.insert_str(i, s);
+        self.cursor_index += s.chars().count();
+    }
+
+    fn open_menu(&mut self) {
+        if self.list_len > 0 {
+            let mut state = ListState::default();
+            state.select(Some(0));
+            self.menu_state = Some(state);
+        }
+    }
+
+    // keys handled while the action menu is opened
+    fn menu_input(
         &mut self,
-        index: &mut Option<usize>,
-    ) -> Result<bool, Box<dyn Error>> {
-        loop {
-            if !poll(Duration::from_millis(30))? {
-                return Ok(false);
+        code: KeyCode,
+        selection: &mut Option<(usize, Action)>,
+    ) -> bool {
+        let menu_len = self.menu.len();
+        if menu_len == 0 {
+            self.menu_state = None;
+            return false;
+        }
+        let state = self.menu_state.as_mut().unwrap();
+        let i = state.selected().unwrap_or(0);
+        match code {
+            KeyCode::Up => {
+                state.select(Some((i + menu_len - 1) % menu_len));
             }
-            if let Event::Key(KeyEvent {
-                code,
-                modifiers,
-                kind: KeyEventKind::Press | KeyEventKind::Repeat,
-                state: _,
-            }) = read()?
-            {
-                if code == KeyCode::Char('c')
-                    && modifiers.contains(KeyModifiers::CONTROL)
+            KeyCode::Down | KeyCode::Tab => {
+                state.select(Some((i + 1) % menu_len));
+            }
+            KeyCode::Enter => {
+                if let (Some(r), Some(action)) =
+                    (self.list_state.selected(), self.menu.get(i))
                 {
-                    return Ok(true);
-                }
-                macro_rules! move_selection {
-                    ($list_len:expr, $state:expr, $i:expr, $dir:expr) => {
-                        if $list_len > 0 {
-                            $state.select(if let Some(i) = $state.selected() {
-                                let i = i as i64 + $dir;
-                                let i = if i < 0 {
-                                    $list_len - 1
-                                } else {
-                                    i as usize % $list_len
-                                };
-                                Some(i)
-                            } else {
-                                None
-                            })
-                        }
-                    };
+                    *selection = Some((r, action.clone()));
+                    self.menu_state = None;
+                    return true;
                 }
-                match code {
-                    KeyCode::Char(ch) => {
-                        self.replace_query();
-                        if self.cursor_index == self.query.len() {
-                            self.query.push(ch);
-                        } else {
-                            // insert Char into Chars
-                            self.query = self
-                                .query
-                                .chars()
-                                .take(self.cursor_index)
-                                .collect::<String>()
-                                + &ch.to_string()
-                                + &self
-                                    .query
-                                    .chars()
-                                    .skip(self.cursor_index)
-                                    .collect::<String>();
+            }
+            _ => self.menu_state = None,
+        }
+        false
+    }
+
+    // move selection by a page without wrapping around
+    fn move_page(&mut self, dir: i64) {
+        if let Some(i) = self.list_state.selected() {
+            let page = self.list_height().max(1) as i64;
+            let i = (i as i64 + dir * page).clamp(0, self.list_len as i64 - 1);
+            self.list_state.select(Some(i as usize));
+        }
+    }
+
+    fn move_selection(&mut self, dir: i64) {
+        if self.list_len > 0 {
+            if let Some(i) = self.list_state.selected() {
+                let i = i as i64 + dir;
+                let i = if i < 0 {
+                    self.list_len - 1
+                } else {
+                    i as usize % self.list_len
+                };
+                self.list_state.select(Some(i));
+            }
+        }
+    }
+
+    // query index of the character at column `x` of the input field
+    fn cursor_at(&self, x: u16) -> usize {
+        let mut width = self
+            .prompt
+            .chars()
+            .fold(0, |acc, x| acc + 1 + (x.len_utf8() > 1) as usize);
+        for (i, ch) in self.query.chars().enumerate() {
+            width += 1 + (ch.len_utf8() > 1) as usize;
+            if width > x as usize {
+                return i;
+            }
+        }
+        self.query.chars().count()
+    }
+
+    fn mouse_input(
+        &mut self,
+        event: MouseEvent,
+        selection: &mut Option<(usize, Action)>,
+    ) -> bool {
+        let (x, y) = (event.column, event.row);
+        match event.kind {
+            MouseEventKind::ScrollUp if self.menu_state.is_some() => {
+                self.menu_input(KeyCode::Up, selection);
+            }
+            MouseEventKind::ScrollDown if self.menu_state.is_some() => {
+                self.menu_input(KeyCode::Down, selection);
+            }
+            MouseEventKind::ScrollUp => self.move_selection(-1),
+            MouseEventKind::ScrollDown => self.move_selection(1),
+            MouseEventKind::Down(MouseButton::Left) => {
+                if contains(self.input_area, x, y) {
+                    self.replace_query();
+                    self.cursor_index = self.cursor_at(x - self.input_area.x);
+                } else if contains(self.list_area, x, y) {
+                    let row = (y - self.list_area.y) as usize;
+                    let double_click = matches!(
+                        self.last_click,
+                        Some((t, r)) if r == row && t.elapsed() < DOUBLE_CLICK
+                    );
+                    self.last_click = Some((Instant::now(), row));
+                    if let Some(state) = &mut self.menu_state {
+                        if row < self.menu.len() {
+                            state.select(Some(row));
+                            if double_click {
+                                return self.menu_input(KeyCode::Enter, selection);
+                            }
                         }
-                        self.cursor_index += 1;
-                        return Ok(false);
-                    }
-                    KeyCode::Backspace | KeyCode::Delete => {
+                    } else if self.list_offset + row < self.list_len {
+                        let i = self.list_offset + row;
                         self.completion = false;
-                        if self.cursor_index > 0 {
-                            self.query = self
-                                .query
-                                .chars()
-                                .take(self.cursor_index - 1)
-                                .chain(
-                                    self.query.chars().skip(self.cursor_index),
-                                )
-                                .collect();
-                            self.cursor_index -= 1;
+                        self.list_state.select(Some(i));
+                        if double_click {
+                            *selection = Some((i, Action::Open));
+                            return true;
                         }
-                        return Ok(false);
-                    }
-                    KeyCode::Up => {
-                        move_selection!(self.list_len, self.list_state, i, -1);
-                        return Ok(false);
                     }
-                    KeyCode::Down => {
-                        move_selection!(self.list_len, self.list_state, i, 1);
-                        return Ok(false);
-                    }
-                    KeyCode::Left => {
-                        self.replace_query();
-                        self.cursor_index -= (self.cursor_index > 0) as usize;
-                        return Ok(false);
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    pub fn handle_event(
+        &mut self,
+        event: Event,
+        selection: &mut Option<(usize, Action)>,
+    ) -> Result<bool, Box<dyn Error>> {
+        if let Event::Mouse(mouse) = event {
+            return Ok(self.mouse_input(mouse, selection));
+        }
+        if let Event::Resize(_, _) = event {
+            self.terminal.autoresize()?;
+            return Ok(false);
+        }
+        if let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press | KeyEventKind::Repeat,
+            state: _,
+        }) = event
+        {
+            if code == KeyCode::Char('c')
+                && modifiers.contains(KeyModifiers::CONTROL)
+            {
+                return Ok(true);
+            }
+            if self.menu_state.is_some() {
+                return Ok(self.menu_input(code, selection));
+            }
+            match code {
+                KeyCode::Char('v') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    // the query is a single line
+                    match self.clipboard.paste() {
+                        Ok(text) => self.insert_str(text.lines().next().unwrap_or("")),
+                        Err(e) => self.status = Some(e.to_string()),
                     }
-                    KeyCode::Right => {
-                        self.replace_query();
-                        self.cursor_index +=
-                            (self.cursor_index < self.query.len()) as usize;
-                        return Ok(false);
+                    return Ok(false);
+                }
+                KeyCode::Char(ch) => {
+                    self.replace_query();
+                    if self.cursor_index == self.query.len() {
+                        self.query.push(ch);
+                    } else {
+                        // insert Char into Chars
+                        self.query = self
+                            .query
+                            .chars()
+                            .take(self.cursor_index)
+                            .collect::<String>()
+                            + &ch.to_string()
+                            + &self
+                                .query
+                                .chars()
+                                .skip(self.cursor_index)
+                                .collect::<String>();
                     }
-                    KeyCode::Enter => {
-                        *index = self.list_state.selected();
-                        return Ok(!index.is_none());
+                    self.cursor_index += 1;
+                    return Ok(false);
+                }
+                KeyCode::Backspace | KeyCode::Delete => {
+                    self.completion = false;
+                    if self.cursor_index > 0 {
+                        self.query = self
+                            .query
+                            .chars()
+                            .take(self.cursor_index - 1)
+                            .chain(
+                                self.query.chars().skip(self.cursor_index),
+                            )
+                            .collect();
+                        self.cursor_index -= 1;
                     }
-                    KeyCode::Tab => {
-                        self.completion = self.list_len > 0;
-                        move_selection!(self.list_len, self.list_state, i, 1);
-                        return Ok(false);
+                    return Ok(false);
+                }
+                KeyCode::Up => {
+                    self.move_selection(-1);
+                    return Ok(false);
+                }
+                KeyCode::Down => {
+                    self.move_selection(1);
+                    return Ok(false);
+                }
+                KeyCode::PageUp => {
+                    self.move_page(-1);
+                    return Ok(false);
+                }
+                KeyCode::PageDown => {
+                    self.move_page(1);
+                    return Ok(false);
+                }
+                KeyCode::Left => {
+                    self.replace_query();
+                    self.cursor_index -= (self.cursor_index > 0) as usize;
+                    return Ok(false);
+                }
+                KeyCode::Right => {
+                    self.replace_query();
+                    if self.cursor_index < self.query.chars().count() {
+                        self.cursor_index += 1;
+                    } else {
+                        self.open_menu();
                     }
-                    KeyCode::Esc => {
-                        // cancel completion
+                    return Ok(false);
+                }
+                KeyCode::Enter
+                    if modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    self.open_menu();
+                    return Ok(false);
+                }
+                KeyCode::Enter => {
+                    *selection =
+                        self.list_state.selected().map(|i| (i, Action::Open));
+                    return Ok(selection.is_some());
+                }
+                KeyCode::Tab if Query::from(&self.query).is_path() => {
+                    // complete the selected path and keep browsing from there
+                    if let Some(s) = self.selected_content.clone() {
+                        self.query = s;
+                        self.cursor_index = self.query.chars().count();
                         self.completion = false;
                     }
-                    _ => return Ok(false),
+                    return Ok(false);
+                }
+                KeyCode::Tab => {
+                    self.completion = self.list_len > 0;
+                    self.move_selection(1);
+                    return Ok(false);
+                }
+                KeyCode::Esc => {
+                    // cancel completion
+                    self.completion = false;
                 }
+                _ => return Ok(false),
             }
         }
+        Ok(false)
+    }
+
+    // forward terminal events to `tx` until the app exits
+    pub fn listen(&mut self, tx: Sender<AppEvent>) {
+        self.listening.store(true, Ordering::SeqCst);
+        let listening = Arc::clone(&self.listening);
+        thread::spawn(move || {
+            // stop reading stdin once the app exits, as the selected result
+            // may need it
+            while listening.load(Ordering::SeqCst) {
+                if let Ok(true) = poll(Duration::from_millis(100)) {
+                    let event = match read() {
+                        Ok(event) => event,
+                        Err(_) => break,
+                    };
+                    if tx.send(AppEvent::Terminal(event)).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
     }
 
     pub fn exit(&mut self) {
+        self.listening.store(false, Ordering::SeqCst);
         if self.running {
             disable_raw_mode().unwrap();
-            execute!(self.terminal.backend_mut(), LeaveAlternateScreen,)
-                .unwrap();
+            execute!(
+                self.terminal.backend_mut(),
+                DisableMouseCapture,
+                LeaveAlternateScreen,
+            )
+            .unwrap();
             self.terminal.show_cursor().unwrap();
             self.running = false
         }
     }
 
     pub fn get_query(&self) -> String {
-        return self.query.clone();
+        self.query.clone()
+    }
+
+    // rows available to the result list in the last drawn frame
+    pub fn list_height(&self) -> usize {
+        self.list_area.height as usize
     }
 
     pub fn set_prompt(&mut self, prompt: &str) -> &mut App {
@@ -264,6 +647,19 @@ impl App {
         self
     }
 
+    pub fn set_status(&mut self, status: Option<String>) -> &mut App {
+        self.status = status;
+        self
+    }
+
+    pub fn clear_query(&mut self) -> &mut App {
+        self.query.clear();
+        self.cursor_index = 0;
+        self.completion = false;
+        self.list_state.select(None);
+        self
+    }
+
     fn fix_selection(&mut self) {
         if self.list_len > 0 {
             match self.list_state.selected() {
@@ -286,6 +682,55 @@ impl Drop for App {
     }
 }
 
+fn contains(area: Rect, x: u16, y: u16) -> bool {
+    area.x <= x && x < area.x + area.width && area.y <= y && y < area.y + area.height
+}
+
+fn make_block(theme: &Theme, compact: bool) -> Block<'static> {
+    if compact {
+        Block::default()
+    } else {
+        Block::default()
+            .borders(theme.borders)
+            .border_type(theme.border_type)
+    }
+}
+
+// split `s` into spans with characters matched by `query` styled
+fn highlight_matches(
+    matcher: &SkimMatcherV2,
+    s: &str,
+    query: &str,
+    style: Style,
+) -> Vec<Span<'static>> {
+    let indices = matcher
+        .fuzzy_indices(s, query.trim())
+        .map(|(_, indices)| indices)
+        .unwrap_or_default();
+    let mut spans = vec![];
+    let mut current = String::new();
+    let mut current_matched = false;
+    for (i, ch) in s.chars().enumerate() {
+        let matched = indices.binary_search(&i).is_ok();
+        if matched != current_matched && !current.is_empty() {
+            let text = std::mem::take(&mut current);
+            spans.push(if current_matched {
+                Span::styled(text, style)
+            } else {
+                Span::raw(text)
+            });
+        }
+        current_matched = matched;
+        current.push(ch);
+    }
+    spans.push(if current_matched {
+        Span::styled(current, style)
+    } else {
+        Span::raw(current)
+    });
+    spans
+}
+
 fn cleanup_terminal() {
     let mut stdout = io::stdout();
 
@@ -297,6 +742,7 @@ fn cleanup_terminal() {
     execute!(stdout, cursor::MoveTo(0, 0)).unwrap();
     execute!(stdout, Clear(ClearType::All)).unwrap();
 
+    execute!(stdout, DisableMouseCapture).unwrap();
     execute!(stdout, LeaveAlternateScreen).unwrap();
     execute!(stdout, cursor::Show).unwrap();
 
@@ -1,4 +1,17 @@
 pub mod backend;
+pub mod bookmarks;
+pub mod clip_history;
+pub mod clipboard;
+pub mod config;
+pub mod dirs;
 pub mod frontend;
+pub mod procs;
+pub mod projects;
+pub mod recent;
+pub mod shell;
+pub mod snippets;
+pub mod theme;
+pub mod urls;
+pub mod windows;
 #[macro_use]
 extern crate lazy_static;
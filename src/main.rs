@@ -1,12 +1,16 @@
+use crossterm::event::Event;
 use launcher::backend::*;
+use launcher::config::*;
 use launcher::frontend::*;
-use std::time::Duration;
+use launcher::{clip_history, clipboard::Clipboard, dirs, snippets, windows};
 use std::{
+    env,
     error::Error,
     io,
     io::Read,
-    sync::{mpsc, Arc, Mutex, TryLockError},
+    sync::{mpsc, Arc, Mutex},
     thread,
+    time::Duration,
 };
 
 macro_rules! mutex {
@@ -15,23 +19,112 @@ macro_rules! mutex {
     };
 }
 
+// `launcher config check` reports config errors.
+// `launcher config dump` prints the effective config and where values come from
+fn config_command(args: &[String], override_path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let (config, sources, error) = Config::load_layers(override_path);
+    match args.first().map(|s| s.as_str()) {
+        Some("check") => match error {
+            None => {
+                println!("ok");
+                Ok(())
+            }
+            Some(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        Some("dump") => {
+            if let Some(e) = error {
+                eprintln!("{}", e);
+            }
+            print!("{}", config.dump(&sources)?);
+            Ok(())
+        }
+        _ => Err("usage: launcher [--config path] config check|dump".into()),
+    }
+}
+
+// `launcher snippet name` prints the expanded snippet, for scripts
+fn snippet_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let name = args.first().ok_or("usage: launcher snippet name")?;
+    let path = dirs::snippets_file().ok_or("no config directory")?;
+    let (_, text) = snippets::read(&path)?
+        .into_iter()
+        .find(|(n, _)| n == name)
+        .ok_or_else(|| format!("no snippet `{}` in {}", name, path))?;
+    print!("{}", snippets::expand(&text, &mut Clipboard::system(), snippets::ask)?);
+    Ok(())
+}
+
+// `launcher clip watch` records the clipboard history until killed.
+// `launcher clip clear` forgets it
+fn clip_command(args: &[String], override_path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    match args.first().map(|s| s.as_str()) {
+        Some("watch") => {
+            let (config, error) = Config::load(override_path);
+            if let Some(e) = error {
+                eprintln!("{}", e);
+            }
+            clip_history::watch(&config.clip_history)
+        }
+        Some("clear") => clip_history::clear(),
+        _ => Err("usage: launcher [--config path] clip watch|clear".into()),
+    }
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut app = App::init("Query>")?;
+    let mut args = env::args().skip(1).collect::<Vec<String>>();
+    let override_path = match args.iter().position(|a| a == "--config") {
+        Some(i) if i + 1 < args.len() => {
+            let path = args.remove(i + 1);
+            args.remove(i);
+            Some(path)
+        }
+        Some(_) => return Err("--config requires a path".into()),
+        None => None,
+    };
+    if args.first().map(|s| s.as_str()) == Some("config") {
+        return config_command(&args[1..], override_path.as_deref());
+    }
+    if args.first().map(|s| s.as_str()) == Some("clip") {
+        return clip_command(&args[1..], override_path.as_deref());
+    }
+    if args.first().map(|s| s.as_str()) == Some("snippet") {
+        return snippet_command(&args[1..]);
+    }
+
+    let (config, config_error) = Config::load(override_path.as_deref());
+    let mut app = App::init("Query>", &config)?;
+    app.set_status(config_error.map(|e| e.to_string()));
+    let config = Arc::new(LiveConfig::new(config));
 
     let cache = Arc::new(Mutex::new(Cache::new()));
     let backend_cache = Arc::clone(&cache);
-    let config = Arc::new(Config::from_file(&CONFIG_PATH));
     let backend_config = Arc::clone(&config);
-    let (query_tx, query_rx) = mpsc::channel::<String>();
-    let (select_tx, select_rx) = mpsc::channel::<LauncherResult>();
+    let selection_config = Arc::clone(&config);
+    let watcher_cache = Arc::clone(&cache);
+    let watcher_config = Arc::clone(&config);
+    let windows_config = Arc::clone(&config);
+    let (query_tx, query_rx) = mpsc::channel::<(String, usize)>();
+    let (select_tx, select_rx) = mpsc::channel::<(LauncherResult, Action)>();
 
     // wait for launching result
     let selection = thread::spawn(move || {
-        let config = Arc::clone(&config);
         let magic_cookie = new_magic_cookie().unwrap();
+        let mut clipboard = Clipboard::system();
         loop {
-            if let Ok(r) = select_rx.recv() {
-                if r.select(&*config, &magic_cookie).unwrap() {
+            if let Ok((r, action)) = select_rx.recv() {
+                let config = selection_config.get();
+                let wait = match r.run_action(&action, &config, &magic_cookie, &mut clipboard) {
+                    Ok(wait) => wait,
+                    // e.g. copying without a clipboard tool
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        true
+                    }
+                };
+                if wait {
                     println!("<Press any key to exit>");
                     io::stdin().lock().read_exact(&mut [0; 1]).unwrap();
                 }
@@ -40,26 +133,123 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     });
 
+    // terminal events and backend notifications
+    let (event_tx, event_rx) = mpsc::channel::<AppEvent>();
+    app.listen(event_tx.clone());
+
+    // reload config when any of its files changes
+    let watcher_tx = event_tx.clone();
+    thread::spawn(move || {
+        let paths = Config::paths(override_path.as_deref());
+        let mut modified = Config::modified(&paths);
+        loop {
+            thread::sleep(Duration::from_millis(500));
+            if Config::modified(&paths) == modified {
+                continue;
+            }
+            modified = Config::modified(&paths);
+
+            // keep the last good config on errors
+            let (config, _, error) = Config::load_layers(override_path.as_deref());
+            if let Some(e) = error {
+                let _ = watcher_tx.send(AppEvent::ConfigReloaded(Some(e.to_string())));
+                continue;
+            }
+            let new_cache = if config.index_differs(&watcher_config.get()) {
+                let (cache, snapshot) = Cache::reconcile(&config, &Snapshot::load());
+                let _ = snapshot.save();
+                Some(cache)
+            } else {
+                None
+            };
+            watcher_config.set(config);
+            {
+                let mut cache = watcher_cache.lock().unwrap();
+                match new_cache {
+                    Some(new_cache) => *cache = new_cache,
+                    // results depend on the config too
+                    None => cache.clear_results(),
+                }
+            }
+            let _ = watcher_tx.send(AppEvent::ConfigReloaded(None));
+        }
+    });
+
+    // running apps, asked apart from searches as window managers are slow
+    thread::spawn(move || loop {
+        windows::refresh(&windows_config.get().window_manager);
+        thread::sleep(Duration::from_secs(1));
+    });
+
     // backend
+    let index_tx = event_tx.clone();
     thread::spawn(move || {
-        let config = Arc::clone(&backend_config);
-        mutex!(backend_cache = Cache::init(&config));
+        // search the last snapshot while it is brought up to date
+        let snapshot = Snapshot::load();
+        mutex!(backend_cache = Cache::from_snapshot(&snapshot, &backend_config.get()));
+        let index_cache = Arc::clone(&backend_cache);
+        let index_config = backend_config.get();
+        thread::spawn(move || {
+            let (cache, snapshot) = Cache::reconcile(&index_config, &snapshot);
+            mutex!(index_cache = cache);
+            let _ = snapshot.save();
+            let _ = index_tx.send(AppEvent::IndexUpdated);
+        });
 
-        while let Ok(s) = query_rx.recv() {
+        while let Ok((s, results_len)) = query_rx.recv() {
             if !s.is_empty() {
-                let config = Arc::clone(&config);
+                let config = backend_config.get();
                 let backend_cache = Arc::clone(&backend_cache);
+                let event_tx = event_tx.clone();
                 thread::spawn(move || {
-                    let mut new_cache = {
-                        let inner = backend_cache.lock().unwrap().clone();
-                        Query::from(s.as_str()).parse(&config, inner).unwrap()
-                    };
-                    let mut inner = backend_cache.lock().unwrap();
-                    for f in new_cache.file_entries {
-                        inner.file_entries.insert(f);
+                    let inner = backend_cache.lock().unwrap().clone();
+                    let generation = inner.generation;
+                    let mut new_cache = Query::from(s.as_str())
+                        .parse_with_len(&config, inner, results_len)
+                        .unwrap();
+                    {
+                        let mut inner = backend_cache.lock().unwrap();
+                        // the cache was replaced or cleared meanwhile, and
+                        // the query is sent again
+                        if inner.generation != generation {
+                            return;
+                        }
+                        for f in new_cache.file_entries {
+                            inner.file_entries.insert(f);
+                        }
+                        if let Some(r) = new_cache.search_results.remove(s.as_str()) {
+                            inner.search_results.insert(s.clone(), r);
+                        }
                     }
-                    if let Some(r) = new_cache.search_results.remove(s.as_str()) {
-                        inner.search_results.insert(s, r);
+                    // results may have been cached by an earlier query
+                    let _ = event_tx.send(AppEvent::ResultsReady(s.clone()));
+
+                    // `git status` is slow, so changes show after the rest
+                    let results = backend_cache.lock().unwrap().get_results(&s);
+                    let projects = results.filter(|results| {
+                        results.iter().any(|r| matches!(r, LauncherResult::Project(_, _, _)))
+                    });
+                    if let Some(results) = projects {
+                        let dirty = dirty_projects(&results);
+                        let mut inner = backend_cache.lock().unwrap();
+                        if inner.generation != generation {
+                            return;
+                        }
+                        inner.mark_dirty(&s, &dirty);
+                        drop(inner);
+                        let _ = event_tx.send(AppEvent::ResultsReady(s.clone()));
+                    }
+
+                    if config.dns_lookup {
+                        if let Some(url) = Query::from(&s).lookup() {
+                            let mut inner = backend_cache.lock().unwrap();
+                            if inner.generation != generation {
+                                return;
+                            }
+                            inner.add_result(&s, url);
+                            drop(inner);
+                            let _ = event_tx.send(AppEvent::ResultsReady(s));
+                        }
                     }
                 });
             }
@@ -68,27 +258,79 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // UI
     let mut results: Arc<Vec<LauncherResult>> = Arc::new(vec![]);
-    loop {
-        let mut index = None;
-        query_tx.send(app.get_query()).unwrap();
-        results = match cache.try_lock() {
-            Ok(r) => r.get_results(&app.get_query()).unwrap_or(results),
-            Err(r) => {
-                if let TryLockError::WouldBlock = r {
-                    results
-                } else {
-                    panic!("{:?}", r);
+    // result waiting for an app to be picked by `Action::OpenWith`
+    let mut open_with: Option<LauncherResult> = None;
+    let mut last_query = String::new();
+    app.update(&results)?;
+    while let Ok(event) = event_rx.recv() {
+        match event {
+            AppEvent::ResultsReady(query) => {
+                if query != app.get_query() {
+                    continue;
+                }
+            }
+            AppEvent::ConfigReloaded(error) => {
+                if error.is_none() {
+                    app.set_config(&config.get())?;
+                    // search again with the new config
+                    last_query.clear();
                 }
+                app.set_status(error);
+            }
+            AppEvent::IndexUpdated => {
+                // results so far came from the snapshot
+                last_query.clear();
             }
-        };
-        if app.update(&results)?.wait_input(&mut index).unwrap() {
-            app.exit();
-            if let Some(i) = index {
-                select_tx.send(results[i].clone())?;
-                selection.join().unwrap();
+            AppEvent::Terminal(event) => {
+                // results are cached by query, with the length of the old size
+                if matches!(event, Event::Resize(_, _)) && config.get().results_fit_screen {
+                    cache.lock().unwrap().clear_results();
+                    last_query.clear();
+                }
+                let mut choice = None;
+                if app.handle_event(event, &mut choice).unwrap() {
+                    if let Some((i, action)) = choice {
+                        let r = results[i].clone();
+                        if action == Action::OpenWith(None) {
+                            open_with = Some(r);
+                            app.set_prompt("Open with>").clear_query();
+                        } else if open_with.is_some() && !matches!(r, LauncherResult::App(_, _)) {
+                            // keep the file and ask again
+                            app.set_status(Some("Pick an app to open with".to_string()));
+                        } else {
+                            let choice = match (open_with.take(), r) {
+                                (Some(file), LauncherResult::App(path, _)) => {
+                                    (file, Action::OpenWith(Some(path)))
+                                }
+                                (_, r) => (r, action),
+                            };
+                            app.exit();
+                            select_tx.send(choice)?;
+                            selection.join().unwrap();
+                            break;
+                        }
+                    } else {
+                        app.exit();
+                        break;
+                    }
+                }
+            }
+        }
+
+        let query = app.get_query();
+        if query != last_query {
+            // not shown again when the query is typed back
+            if Query::from(&last_query).is_live() {
+                cache.lock().unwrap().remove_results(&last_query);
             }
-            break;
+            query_tx
+                .send((query.clone(), config.get().results_len(app.list_height())))
+                .unwrap();
+            last_query = query;
         }
+        // waits for the lock, as `ResultsReady` is sent only once
+        results = cache.lock().unwrap().get_results(&last_query).unwrap_or(results);
+        app.update(&results)?;
     }
-    return Ok::<(), Box<dyn Error>>(());
+    Ok::<(), Box<dyn Error>>(())
 }
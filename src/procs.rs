@@ -0,0 +1,157 @@
+// Running processes, for `:kill`. Read from `/proc` on Linux and from `ps`
+// elsewhere
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs, io,
+    path::Path,
+    process::Command,
+};
+
+#[derive(Debug, Clone)]
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+    pub cmdline: String,
+    pub cpu: f32, // percent of one core, averaged over the process lifetime
+    pub rss: u64, // resident memory in KiB
+}
+
+pub trait ProcessSource {
+    fn list(&self) -> io::Result<Vec<Process>>;
+}
+
+pub struct Procfs;
+pub struct Ps;
+
+// the source for this platform
+pub fn source() -> Box<dyn ProcessSource> {
+    if cfg!(target_os = "linux") {
+        Box::new(Procfs)
+    } else {
+        Box::new(Ps)
+    }
+}
+
+// `signal` as `kill` takes it, e.g. `TERM` or `KILL`
+pub fn kill(pid: u32, signal: &str) -> Result<(), Box<dyn Error>> {
+    let status = Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg(pid.to_string())
+        .status()?;
+    if !status.success() {
+        return Err(format!("failed to send SIG{} to {}", signal, pid).into());
+    }
+    Ok(())
+}
+
+// clock ticks per second of `/proc/*/stat` times
+fn clock_ticks() -> f32 {
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as f32,
+        _ => 100.0,
+    }
+}
+
+impl ProcessSource for Procfs {
+    fn list(&self) -> io::Result<Vec<Process>> {
+        let ticks = clock_ticks();
+        let uptime: f32 = fs::read_to_string("/proc/uptime")?
+            .split_whitespace()
+            .next()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default();
+        let mut processes = vec![];
+        for entry in fs::read_dir("/proc")?.flatten() {
+            let pid = match entry.file_name().to_str().and_then(|s| s.parse().ok()) {
+                Some(pid) => pid,
+                None => continue,
+            };
+            // processes may exit while being read
+            if let Some(process) = Procfs::read(&entry.path(), pid, uptime, ticks) {
+                processes.push(process);
+            }
+        }
+        Ok(processes)
+    }
+}
+
+impl Procfs {
+    fn read(dir: &Path, pid: u32, uptime: f32, ticks: f32) -> Option<Process> {
+        let stat = fs::read_to_string(dir.join("stat")).ok()?;
+        // the name is in parentheses and may contain anything, even `)`
+        let open = stat.find('(')?;
+        let close = stat.rfind(')')?;
+        let name = stat[open + 1..close].to_string();
+        let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+        let field = |i: usize| -> f32 { fields.get(i).and_then(|s| s.parse().ok()).unwrap_or(0.0) };
+        // fields 14, 15 and 22 of proc(5), counted from the state
+        let busy = (field(11) + field(12)) / ticks;
+        let elapsed = uptime - field(19) / ticks;
+        let cpu = if elapsed > 0.0 { busy * 100.0 / elapsed } else { 0.0 };
+
+        let rss = fs::read_to_string(dir.join("status"))
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))
+            .and_then(|s| s.trim().trim_end_matches("kB").trim().parse().ok())
+            .unwrap_or(0); // kernel threads
+        let cmdline = fs::read(dir.join("cmdline"))
+            .map(|bytes| {
+                String::from_utf8_lossy(&bytes)
+                    .split('\0')
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+        Some(Process {
+            pid,
+            name,
+            cmdline,
+            cpu,
+            rss,
+        })
+    }
+}
+
+impl ProcessSource for Ps {
+    fn list(&self) -> io::Result<Vec<Process>> {
+        // `comm` may contain spaces, so names are read on their own
+        let names: HashMap<u32, String> = ps(&["pid=", "comm="])?
+            .into_iter()
+            .filter_map(|(pid, rest)| {
+                let name = Path::new(rest.trim()).file_name()?.to_string_lossy();
+                Some((pid, name.to_string()))
+            })
+            .collect();
+        Ok(ps(&["pid=", "pcpu=", "rss=", "args="])?
+            .into_iter()
+            .filter_map(|(pid, rest)| {
+                let mut fields = rest.trim_start().splitn(3, char::is_whitespace);
+                let cpu = fields.next()?.parse().ok()?;
+                let rss = fields.next()?.parse().ok()?;
+                let cmdline = fields.next().unwrap_or("").trim().to_string();
+                Some(Process {
+                    pid,
+                    name: names.get(&pid).cloned().unwrap_or_else(|| cmdline.clone()),
+                    cmdline,
+                    cpu,
+                    rss,
+                })
+            })
+            .collect())
+    }
+}
+
+// `ps -ax` lines as the pid and the other columns
+fn ps(columns: &[&str]) -> io::Result<Vec<(u32, String)>> {
+    let output = Command::new("ps").arg("-ax").arg("-o").arg(columns.join(",")).output()?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, rest) = line.trim_start().split_once(char::is_whitespace)?;
+            Some((pid.parse().ok()?, rest.to_string()))
+        })
+        .collect())
+}
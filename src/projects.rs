@@ -0,0 +1,81 @@
+// Git checkouts under the `[projects]` roots
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+// Repositories under `root`, and every directory read to find them. The
+// search does not go into repositories, hidden directories or deeper than
+// `max_depth`
+pub fn discover(root: &str, max_depth: usize) -> (Vec<String>, Vec<String>) {
+    fn walk(dir: &Path, depth: usize, repos: &mut Vec<String>, dirs: &mut Vec<String>) {
+        let path = match dir.to_str() {
+            Some(path) => path.to_string(),
+            None => return,
+        };
+        // `.git` is a file in worktrees and submodules
+        if dir.join(".git").exists() {
+            repos.push(path);
+            return;
+        }
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        dirs.push(path);
+        if depth == 0 {
+            return;
+        }
+        for entry in entries.flatten() {
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            // not through symlinks, which may loop
+            if !hidden && entry.file_type().is_ok_and(|t| t.is_dir()) {
+                walk(&entry.path(), depth - 1, repos, dirs);
+            }
+        }
+    }
+
+    let (mut repos, mut dirs) = (vec![], vec![]);
+    walk(Path::new(root), max_depth, &mut repos, &mut dirs);
+    (repos, dirs)
+}
+
+// Checked out branch, or the short commit when detached. Read from `HEAD`,
+// which is faster than asking git
+pub fn branch(repo: &str) -> Option<String> {
+    let head = fs::read_to_string(git_dir(repo)?.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        ),
+        None => head.get(..7).map(str::to_string),
+    }
+}
+
+// Tracked files were changed. Untracked files are not counted, as listing
+// them can take long in large checkouts
+pub fn is_dirty(repo: &str) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|output| output.status.success() && !output.stdout.is_empty())
+}
+
+// `.git`, or where `gitdir:` in a `.git` file points to
+fn git_dir(repo: &str) -> Option<PathBuf> {
+    let git = Path::new(repo).join(".git");
+    if git.is_dir() {
+        return Some(git);
+    }
+    let link = fs::read_to_string(&git).ok()?;
+    let target = link.trim().strip_prefix("gitdir: ")?;
+    Some(Path::new(repo).join(target))
+}
@@ -0,0 +1,153 @@
+// Recently used files, newest first. Merged from the freedesktop
+// `recently-used.xbel`, files opened through the launcher and, if
+// configured, the most recently modified files under some directories
+use crate::config::RecentConfig;
+use crate::dirs;
+use ignore::WalkBuilder;
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+// launch history entries kept
+const HISTORY_LEN: usize = 500;
+
+// paths of existing files, at most `config.size`
+pub fn list(config: &RecentConfig) -> Vec<String> {
+    let mut times: HashMap<String, u64> = HashMap::new();
+    let mut add = |path: String, time: u64| {
+        let t = times.entry(path).or_default();
+        *t = (*t).max(time);
+    };
+    if config.xbel {
+        for path in dirs::recently_used_file().iter() {
+            read_xbel(path).into_iter().for_each(|(p, t)| add(p, t));
+        }
+    }
+    read_history().into_iter().for_each(|(p, t)| add(p, t));
+    for dir in &config.dirs {
+        scan(dir, config.max_depth).into_iter().for_each(|(p, t)| add(p, t));
+    }
+
+    let mut files: Vec<(String, u64)> = times
+        .into_iter()
+        .filter(|(path, _)| Path::new(path).is_file())
+        .collect();
+    files.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    files.truncate(config.size);
+    files.into_iter().map(|(path, _)| path).collect()
+}
+
+// Remember that `path` was opened from the launcher
+pub fn record(path: &str) -> Result<(), Box<dyn Error>> {
+    let history = match dirs::history_file() {
+        Some(history) => history,
+        None => return Ok(()),
+    };
+    let mut entries = read_history();
+    entries.retain(|(p, _)| p != path);
+    entries.insert(0, (path.to_string(), now()));
+    entries.truncate(HISTORY_LEN);
+
+    dirs::ensure_parent(&history)?;
+    let tmp = format!("{}.{}", history, std::process::id());
+    let lines: String = entries
+        .iter()
+        .map(|(p, t)| format!("{}\t{}\n", t, p))
+        .collect();
+    fs::write(&tmp, lines)?;
+    fs::rename(tmp, history)?;
+    Ok(())
+}
+
+// `seconds<TAB>path` lines, newest first
+fn read_history() -> Vec<(String, u64)> {
+    dirs::history_file()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|s| {
+            s.lines()
+                .filter_map(|line| {
+                    let (time, path) = line.split_once('\t')?;
+                    Some((path.to_string(), time.parse().ok()?))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// `<bookmark href="file:///..." modified="..." visited="...">` elements.
+// Other schemes are skipped
+fn read_xbel(path: &str) -> Vec<(String, u64)> {
+    lazy_static! {
+        static ref BOOKMARK: Regex = Regex::new(r"<bookmark\s[^>]*>").unwrap();
+        static ref ATTRIBUTE: Regex = Regex::new(r#"(\w+)="([^"]*)""#).unwrap();
+    }
+    let xml = match fs::read_to_string(path) {
+        Ok(xml) => xml,
+        Err(_) => return vec![],
+    };
+    BOOKMARK
+        .find_iter(&xml)
+        .filter_map(|element| {
+            let attributes: HashMap<&str, &str> = ATTRIBUTE
+                .captures_iter(element.as_str())
+                .map(|c| (c.get(1).unwrap().as_str(), c.get(2).unwrap().as_str()))
+                .collect();
+            let file = Url::parse(&attributes.get("href")?.replace("&amp;", "&"))
+                .ok()?
+                .to_file_path()
+                .ok()?;
+            let time = ["visited", "modified", "added"]
+                .iter()
+                .filter_map(|name| parse_time(attributes.get(name)?))
+                .max()?;
+            Some((file.to_str()?.to_string(), time))
+        })
+        .collect()
+}
+
+// files under `dir`, with their mtime. Hidden and ignored files are skipped
+fn scan(dir: &str, max_depth: usize) -> Vec<(String, u64)> {
+    WalkBuilder::new(dir)
+        .max_depth(Some(max_depth))
+        .require_git(false)
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|entry| {
+            let time = entry.metadata().ok()?.modified().ok()?;
+            let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+            Some((entry.path().to_str()?.to_string(), secs))
+        })
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// seconds since the epoch of a UTC `2024-03-05T10:20:30.123456Z`
+fn parse_time(s: &str) -> Option<u64> {
+    let (date, time) = s.trim_end_matches('Z').split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|n| n.parse::<i64>().ok());
+    let (y, m, d) = (date.next()??, date.next()??, date.next()??);
+    let mut time = time.split('.').next()?.splitn(3, ':').map(|n| n.parse::<i64>().ok());
+    let (h, min, sec) = (time.next()??, time.next()??, time.next()??);
+
+    // days from civil, http://howardhinnant.github.io/date_algorithms.html
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    u64::try_from(days * 86400 + h * 3600 + min * 60 + sec).ok()
+}
@@ -0,0 +1,306 @@
+// Shell-like word splitting, quoting and expansion, for what is typed in the
+// query and paths in the config
+use crate::dirs::HOME_PATH;
+use std::{
+    env,
+    ffi::{CStr, CString},
+    mem,
+    path::Path,
+    ptr,
+};
+
+// Split on unquoted whitespace. 'single quotes' are literal, "double quotes"
+// and bare words take `\` escapes. `None` if a quote is left open
+pub fn split(s: &str) -> Option<Vec<String>> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(w) = word.take() {
+                    words.push(w);
+                }
+            }
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => w.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        // only these are escaped inside double quotes
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => w.push(c),
+                            c => {
+                                w.push('\\');
+                                w.push(c);
+                            }
+                        },
+                        c => w.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                // a trailing backslash is kept as is
+                let c = chars.next().unwrap_or('\\');
+                word.get_or_insert_with(String::new).push(c);
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+// Quote `s` so the shell reads it back as one word
+pub fn quote(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./=:,+@%".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+// `words` as a command line
+pub fn join<T: AsRef<str>>(words: &[T]) -> String {
+    words
+        .iter()
+        .map(|w| quote(w.as_ref()))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Expand a leading `~` or `~user`, and `$VAR`, `${VAR}` and `${VAR:-default}`
+// anywhere. Unset variables expand to nothing, as in the shell. Anything
+// that cannot be expanded, like an unknown user, is kept as typed
+pub fn expand(s: &str) -> String {
+    let (head, rest) = match s.strip_prefix('~') {
+        Some(rest) => {
+            let (user, rest) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            let home = if user.is_empty() {
+                HOME_PATH.clone()
+            } else {
+                user_home(user)
+            };
+            match home {
+                Some(home) => (home, rest),
+                None => (String::new(), s),
+            }
+        }
+        None => (String::new(), s),
+    };
+    head + &expand_vars(rest)
+}
+
+// `program` is found in `$PATH`
+pub fn in_path(program: &str) -> bool {
+    env::var("PATH")
+        .unwrap_or_default()
+        .split(':')
+        .any(|dir| Path::new(dir).join(program).is_file())
+}
+
+fn expand_vars(s: &str) -> String {
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        if let Some(braced) = after.strip_prefix('{') {
+            match closing_brace(braced) {
+                Some(end) => {
+                    let (name, default) = match braced[..end].split_once(":-") {
+                        Some((name, default)) => (name, Some(default)),
+                        None => (&braced[..end], None),
+                    };
+                    match (env::var(name).ok().filter(|v| !v.is_empty()), default) {
+                        (Some(value), _) => out.push_str(&value),
+                        (None, Some(default)) => out.push_str(&expand_vars(default)),
+                        (None, None) => {}
+                    }
+                    rest = &braced[end + 1..];
+                }
+                // unclosed `${`
+                None => {
+                    out.push_str(&rest[i..]);
+                    rest = "";
+                }
+            }
+        } else if after.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            let end = after.find(|c| !is_name(c)).unwrap_or(after.len());
+            out.push_str(&env::var(&after[..end]).unwrap_or_default());
+            rest = &after[end..];
+        } else {
+            // `$` not followed by a name, e.g. `$5`
+            out.push('$');
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// index of the `}` closing `s`, past nested `${...}` in defaults
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+// home directory of `user` from the user database. Not only /etc/passwd,
+// which lists no regular users on macOS
+fn user_home(user: &str) -> Option<String> {
+    let name = CString::new(user).ok()?;
+    let mut buf: Vec<libc::c_char> = vec![0; 1024];
+    loop {
+        let mut pwd: libc::passwd = unsafe { mem::zeroed() };
+        let mut result = ptr::null_mut();
+        let err = unsafe {
+            libc::getpwnam_r(name.as_ptr(), &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result)
+        };
+        if err == libc::ERANGE && buf.len() < 1 << 20 {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if err != 0 || result.is_null() || pwd.pw_dir.is_null() {
+            return None;
+        }
+        let dir = unsafe { CStr::from_ptr(pwd.pw_dir) };
+        return Some(dir.to_string_lossy().into_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_words() {
+        let table: [(&str, &[&str]); 12] = [
+            ("htop -d 5", &["htop", "-d", "5"]),
+            ("  a \t b  ", &["a", "b"]),
+            ("'a b' c", &["a b", "c"]),
+            (r#"'a \ "b"'"#, &[r#"a \ "b""#]),
+            (r#""a\"b""#, &["a\"b"]),
+            (r#""a\nb \$x""#, &[r"a\nb $x"]),
+            (r"a\ b", &["a b"]),
+            (r#"x'y'"z""#, &["xyz"]),
+            (r#"'' """#, &["", ""]),
+            ("", &[]),
+            // a trailing backslash is kept
+            (r"a\", &[r"a\"]),
+            (r"a \", &["a", r"\"]),
+        ];
+        for (typed, words) in table {
+            assert_eq!(split(typed).unwrap(), words, "{}", typed);
+        }
+    }
+
+    #[test]
+    fn unclosed_quotes() {
+        for typed in ["'a", "a 'b c", "\"a", r#""a\""#, r#""a\"#] {
+            assert_eq!(split(typed), None, "{}", typed);
+        }
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("plain-word_1.txt"), "plain-word_1.txt");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(join(&["ls", "-l", "my file"]), "ls -l 'my file'");
+    }
+
+    #[test]
+    fn join_round_trip() {
+        let words = ["a b", "it's", "\"q\"", "$HOME", "back\\slash", "", "\t", "*"];
+        assert_eq!(split(&join(&words)).unwrap(), words);
+        for typed in ["htop -d 5", r#"grep -e "a b" 'c d'"#, r"a\ b 'x'\''y'"] {
+            let words = split(typed).unwrap();
+            assert_eq!(split(&join(&words)).unwrap(), words, "{}", typed);
+        }
+    }
+
+    // tests run in parallel, so each uses its own variables
+    #[test]
+    fn home() {
+        let home = env::var("HOME").unwrap();
+        assert_eq!(expand("~"), home);
+        assert_eq!(expand("~/code"), format!("{}/code", home));
+        // only leading
+        assert_eq!(expand("a/~/b"), "a/~/b");
+    }
+
+    #[test]
+    fn user_home_dirs() {
+        let root = if cfg!(target_os = "macos") { "/var/root" } else { "/root" };
+        assert_eq!(expand("~root"), root);
+        assert_eq!(expand("~root/x"), format!("{}/x", root));
+        assert_eq!(expand("~no_such_user_zz/x"), "~no_such_user_zz/x");
+    }
+
+    #[test]
+    fn variables() {
+        env::set_var("SHELL_TEST_V", "vee");
+        assert_eq!(expand("$SHELL_TEST_V/x"), "vee/x");
+        assert_eq!(expand("a${SHELL_TEST_V}b"), "aveeb");
+        assert_eq!(expand("$SHELL_TEST_V-$SHELL_TEST_V"), "vee-vee");
+    }
+
+    #[test]
+    fn unset_variables() {
+        env::remove_var("SHELL_TEST_UNSET");
+        assert_eq!(expand("$SHELL_TEST_UNSET/x"), "/x");
+        assert_eq!(expand("${SHELL_TEST_UNSET}/x"), "/x");
+    }
+
+    #[test]
+    fn defaults() {
+        env::set_var("SHELL_TEST_D", "dee");
+        env::remove_var("SHELL_TEST_D_UNSET");
+        assert_eq!(expand("${SHELL_TEST_D:-x}"), "dee");
+        assert_eq!(expand("${SHELL_TEST_D_UNSET:-x}/y"), "x/y");
+        assert_eq!(expand("${SHELL_TEST_D_UNSET:-}"), "");
+        // set but empty counts as unset, as with `:-` in the shell
+        env::set_var("SHELL_TEST_D_EMPTY", "");
+        assert_eq!(expand("${SHELL_TEST_D_EMPTY:-x}"), "x");
+    }
+
+    #[test]
+    fn nested_defaults() {
+        env::remove_var("SHELL_TEST_N_A");
+        env::set_var("SHELL_TEST_N_B", "bee");
+        assert_eq!(expand("${SHELL_TEST_N_A:-${SHELL_TEST_N_B}}/x"), "bee/x");
+        assert_eq!(
+            expand("${SHELL_TEST_N_A:-${SHELL_TEST_N_A:-deep}}/x"),
+            "deep/x"
+        );
+        assert_eq!(expand("${SHELL_TEST_N_A:-~/x}"), "~/x");
+    }
+
+    #[test]
+    fn lone_dollar() {
+        assert_eq!(expand("$"), "$");
+        assert_eq!(expand("a$"), "a$");
+        assert_eq!(expand("$5 and $ x"), "$5 and $ x");
+        assert_eq!(expand("${unclosed"), "${unclosed");
+    }
+}
@@ -0,0 +1,103 @@
+// Named text snippets from `snippets.toml`, as `name = "text"`. Text may
+// contain placeholders:
+//   {date}           today, as `date +%F`
+//   {date:%H:%M}     any `date` format
+//   {clipboard}      the clipboard content
+//   {prompt}         asked when the snippet is used
+//   {prompt:Label}   same, with a label
+// `{{` and `}}` are literal braces. Unknown placeholders are kept as typed
+use crate::clipboard::Clipboard;
+use std::{error::Error, fs, io, process::Command};
+use toml::{value::Table, Value};
+
+pub fn read(path: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let table: Table = toml::from_str(&fs::read_to_string(path)?)?;
+    table
+        .into_iter()
+        .map(|(name, text)| match text {
+            Value::String(text) => Ok((name, text)),
+            _ => Err(format!("{}: snippet `{}` is not a string", path, name).into()),
+        })
+        .collect()
+}
+
+// `prompt` is called with the label of each `{prompt}` and returns the answer
+pub fn expand<F>(
+    text: &str,
+    clipboard: &mut Clipboard,
+    mut prompt: F,
+) -> Result<String, Box<dyn Error>>
+where
+    F: FnMut(&str) -> Result<String, Box<dyn Error>>,
+{
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let brace = &rest[i..i + 1];
+        let after = &rest[i + 1..];
+        if after.starts_with(brace) {
+            // `{{` or `}}`
+            out.push_str(brace);
+            rest = &after[1..];
+            continue;
+        }
+        let end = match after.find('}') {
+            Some(end) if brace == "{" => end,
+            _ => {
+                out.push_str(brace);
+                rest = after;
+                continue;
+            }
+        };
+        let (name, arg) = match after[..end].split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (&after[..end], None),
+        };
+        match name {
+            "date" => out.push_str(&date(arg.unwrap_or("%F"))?),
+            "clipboard" => out.push_str(&clipboard.paste()?),
+            "prompt" => out.push_str(&prompt(arg.unwrap_or("Value"))?),
+            _ => out.push_str(&rest[i..i + end + 2]),
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+// local time, in `date`'s format
+fn date(format: &str) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("date").arg(format!("+{}", format)).output()?;
+    Ok(String::from_utf8(output.stdout)?.trim_end_matches('\n').to_string())
+}
+
+// asks on the terminal. Prompts go to stderr, so that stdout keeps only the
+// snippet when it is printed
+pub fn ask(label: &str) -> Result<String, Box<dyn Error>> {
+    eprint!("{}: ", label);
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipboard_and_prompts() {
+        let mut clipboard = Clipboard::Memory("copied".to_string());
+        let text = expand("{clipboard} to {prompt:Who} {{x}}", &mut clipboard, |label| {
+            Ok(format!("<{}>", label))
+        });
+        assert_eq!(text.unwrap(), "copied to <Who> {x}");
+    }
+
+    #[test]
+    fn missing_clipboard_is_an_error() {
+        let mut clipboard = Clipboard::Unavailable;
+        assert!(expand("{clipboard}", &mut clipboard, |_| Ok(String::new())).is_err());
+        assert_eq!(expand("plain", &mut clipboard, |_| Ok(String::new())).unwrap(), "plain");
+    }
+}
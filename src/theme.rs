@@ -0,0 +1,160 @@
+use crate::backend::LauncherResult;
+use crate::config::ThemeConfig;
+use tui::{
+    style::{Color, Modifier, Style},
+    widgets::{BorderType, Borders},
+};
+
+pub struct Theme {
+    pub app: Style,
+    pub bin: Style,
+    pub file: Style,
+    pub url: Style,
+    pub command: Style,
+    pub highlight: Style,
+    pub matched: Style,
+    pub scrollbar: Style,
+    pub status: Style,
+    pub borders: Borders,
+    pub border_type: BorderType,
+    pub highlight_symbol: String,
+}
+
+impl Theme {
+    pub fn dark() -> Theme {
+        Theme {
+            app: Style::default().fg(Color::Green),
+            bin: Style::default().fg(Color::Yellow),
+            file: Style::default().fg(Color::Blue),
+            url: Style::default().fg(Color::Cyan),
+            command: Style::default().fg(Color::Magenta),
+            highlight: Style::default()
+                .fg(Color::Black)
+                .bg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            matched: Style::default()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+            scrollbar: Style::default().fg(Color::DarkGray),
+            status: Style::default().fg(Color::LightRed),
+            borders: Borders::ALL,
+            border_type: BorderType::Plain,
+            highlight_symbol: ">> ".to_string(),
+        }
+    }
+
+    pub fn light() -> Theme {
+        Theme {
+            app: Style::default().fg(Color::Green),
+            bin: Style::default().fg(Color::Red),
+            file: Style::default().fg(Color::Blue),
+            url: Style::default().fg(Color::Magenta),
+            command: Style::default().fg(Color::DarkGray),
+            highlight: Style::default()
+                .fg(Color::White)
+                .bg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            matched: Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+            scrollbar: Style::default().fg(Color::Gray),
+            status: Style::default().fg(Color::Red),
+            borders: Borders::ALL,
+            border_type: BorderType::Plain,
+            highlight_symbol: ">> ".to_string(),
+        }
+    }
+
+    pub fn from_config(config: &ThemeConfig) -> Theme {
+        let mut theme = match config.preset.as_str() {
+            "light" => Theme::light(),
+            _ => Theme::dark(),
+        };
+
+        macro_rules! set_fg {
+            ($field:ident) => {
+                if let Some(c) = config.$field.as_deref().and_then(parse_color) {
+                    theme.$field = theme.$field.fg(c);
+                }
+            };
+        }
+        set_fg!(app);
+        set_fg!(bin);
+        set_fg!(file);
+        set_fg!(url);
+        set_fg!(command);
+        if let Some(c) = config.highlight_fg.as_deref().and_then(parse_color) {
+            theme.highlight = theme.highlight.fg(c);
+        }
+        if let Some(c) = config.highlight_bg.as_deref().and_then(parse_color) {
+            theme.highlight = theme.highlight.bg(c);
+        }
+        if let Some(c) = config.match_fg.as_deref().and_then(parse_color) {
+            theme.matched = theme.matched.fg(c);
+        }
+        if let Some(border) = &config.border {
+            theme.borders = Borders::ALL;
+            match border.as_str() {
+                "rounded" => theme.border_type = BorderType::Rounded,
+                "double" => theme.border_type = BorderType::Double,
+                "thick" => theme.border_type = BorderType::Thick,
+                "none" => theme.borders = Borders::NONE,
+                _ => theme.border_type = BorderType::Plain,
+            }
+        }
+        if let Some(symbol) = &config.highlight_symbol {
+            theme.highlight_symbol = symbol.clone();
+        }
+        theme
+    }
+
+    pub fn kind_style(&self, result: &LauncherResult) -> Style {
+        match result {
+            LauncherResult::App(_, _) => self.app,
+            LauncherResult::Bin(_, _)
+            | LauncherResult::Exec(_, _)
+            | LauncherResult::Process(_) => self.bin,
+            LauncherResult::File(_) | LauncherResult::Project(_, _, _) => self.file,
+            LauncherResult::Url(_) | LauncherResult::Bookmark(_, _) => self.url,
+            LauncherResult::Command(_, _)
+            | LauncherResult::Snippet(_, _)
+            | LauncherResult::Clip(_) => self.command,
+        }
+    }
+}
+
+pub fn parse_color(s: &str) -> Option<Color> {
+    let s = s.trim().to_lowercase();
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel =
+            |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(i) = s.parse::<u8>() {
+        return Some(Color::Indexed(i));
+    }
+    let color = match s.replace(['_', '-', ' '], "").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    };
+    Some(color)
+}
@@ -0,0 +1,214 @@
+// Offline URL detection and normalisation. Nothing typed is sent to the
+// network
+use std::{collections::BTreeMap, net::Ipv6Addr};
+use url::{Host, Url};
+
+// schemes used without `//`
+const OPAQUE_SCHEMES: [&str; 7] = ["mailto", "file", "tel", "sms", "magnet", "data", "about"];
+
+// common file extensions, many also top level domains, like `.rs` or `.sh`
+const FILE_EXTENSIONS: [&str; 20] = [
+    "c", "cc", "cpp", "go", "h", "js", "json", "lock", "log", "md", "mov", "pdf", "pl", "ps", "py",
+    "rb", "rs", "sh", "txt", "zip",
+];
+
+// `https://x.y/z`, `mailto:me@x.y`, `10.0.0.1:8080`, `[::1]:3000/a`,
+// `localhost:8000` or a domain under a known public suffix, like
+// `example.co.uk/path`. Not `main.rs`, see `is_file_name`
+pub fn is_url(query: &str) -> bool {
+    if query.is_empty() || query.contains(char::is_whitespace) {
+        return false;
+    }
+    if has_scheme(query) || query.parse::<Ipv6Addr>().is_ok() {
+        return true;
+    }
+    // `url` takes `1` for an IPv4 address, so IPs are also checked as typed
+    let typed = match typed_host(query) {
+        Some(host) => host,
+        None => return false,
+    };
+    match parse_host(query) {
+        Some(Host::Ipv4(ip)) => typed == ip.to_string(),
+        Some(Host::Ipv6(_)) => true,
+        Some(Host::Domain(domain)) => {
+            domain == "localhost" || (has_public_suffix(&domain) && !is_file_name(query))
+        }
+        None => false,
+    }
+}
+
+// `name.ext` typed without scheme, port, path or `www.`, where `.ext` is a
+// common file extension. More likely a file than a host
+pub fn is_file_name(query: &str) -> bool {
+    if query.contains(['/', ':', '?', '#', '@']) || query.starts_with("www.") {
+        return false;
+    }
+    match query.rsplit_once('.') {
+        Some((name, ext)) => {
+            !name.is_empty() && FILE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+        }
+        None => false,
+    }
+}
+
+// Domain of a URL typed without scheme, e.g. `nas.lan` in `nas.lan:5000`
+pub fn domain(query: &str) -> Option<String> {
+    typed_host(query)?;
+    match parse_host(query)? {
+        Host::Domain(domain) => Some(domain),
+        _ => None,
+    }
+}
+
+// URL to open for a query `is_url` accepts. Schemes are kept, `https` is
+// the default, except for localhost and IPs, which seldom have certificates
+pub fn normalize(query: &str) -> String {
+    if has_scheme(query) {
+        return query.to_string();
+    }
+    if let Ok(ip) = query.parse::<Ipv6Addr>() {
+        return format!("http://[{}]", ip);
+    }
+    let scheme = match parse_host(query) {
+        Some(Host::Domain(domain)) if domain != "localhost" => "https",
+        Some(_) => "http",
+        None => "https",
+    };
+    format!("{}://{}", scheme, query)
+}
+
+// `gh:owner/repo` to `https://github.com/owner/repo` with
+// `"gh:" = "https://github.com/{}"`. The longest matching prefix wins
+pub fn expand_shorthand(query: &str, shorthands: &BTreeMap<String, String>) -> Option<String> {
+    shorthands
+        .iter()
+        .filter(|(prefix, _)| query.len() > prefix.len() && query.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(prefix, template)| template.replace("{}", &query[prefix.len()..]))
+}
+
+// `x://...`, or `mailto:` and the like. Not `localhost:8000`
+fn has_scheme(query: &str) -> bool {
+    match Url::parse(query) {
+        Ok(url) => {
+            let scheme = url.scheme();
+            query[scheme.len()..].starts_with("://") || OPAQUE_SCHEMES.contains(&scheme)
+        }
+        Err(_) => false,
+    }
+}
+
+fn parse_host(query: &str) -> Option<Host> {
+    let url = Url::parse(&format!("http://{}", query)).ok()?;
+    url.host().map(|host| host.to_owned())
+}
+
+// Host as typed, without port, path, query or fragment. `None` for user
+// info, which is more likely an email address
+fn typed_host(query: &str) -> Option<&str> {
+    let authority = query.split(['/', '?', '#']).next()?;
+    if authority.contains('@') {
+        return None;
+    }
+    if authority.starts_with('[') {
+        return authority.find(']').map(|i| &authority[..=i]);
+    }
+    match authority.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => Some(host),
+        Some(_) => None,
+        None => Some(authority),
+    }
+    .filter(|host| !host.is_empty())
+}
+
+// a name under a suffix of the embedded public suffix list
+fn has_public_suffix(domain: &str) -> bool {
+    let domain = domain.trim_end_matches('.');
+    match psl::suffix(domain.as_bytes()) {
+        Some(suffix) => suffix.is_known() && suffix.as_bytes().len() < domain.len(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_table() {
+        let table = [
+            // https is the default
+            ("example.com", "https://example.com"),
+            ("example.co.uk/path?q=1", "https://example.co.uk/path?q=1"),
+            ("www.example.com", "https://www.example.com"),
+            // schemes are kept as typed
+            ("http://example.com", "http://example.com"),
+            ("file:///etc/hosts", "file:///etc/hosts"),
+            ("mailto:me@example.com", "mailto:me@example.com"),
+            ("ssh://git@example.com:22/repo", "ssh://git@example.com:22/repo"),
+            ("vscode://file/home/me/a.rs", "vscode://file/home/me/a.rs"),
+            // IPv6 literals
+            ("::1", "http://[::1]"),
+            ("fe80::1", "http://[fe80::1]"),
+            ("[::1]", "http://[::1]"),
+            ("[::1]:3000/a", "http://[::1]:3000/a"),
+            // host:port
+            ("example.com:8443", "https://example.com:8443"),
+            ("10.0.0.1:8080", "http://10.0.0.1:8080"),
+            ("localhost:8000", "http://localhost:8000"),
+            ("localhost", "http://localhost"),
+        ];
+        for (query, url) in table {
+            assert!(is_url(query), "{}", query);
+            assert_eq!(normalize(query), url, "{}", query);
+        }
+    }
+
+    #[test]
+    fn not_urls() {
+        for query in ["", "firefox", "two words.com", "me@example.com", "1", "a:b", "x.notatld"] {
+            assert!(!is_url(query), "{}", query);
+        }
+    }
+
+    #[test]
+    fn shorthand_table() {
+        let shorthands: BTreeMap<String, String> = [
+            ("gh:", "https://github.com/{}"),
+            ("#", "https://tracker.example.com/issue/{}"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let table = [
+            ("gh:owner/repo", Some("https://github.com/owner/repo")),
+            ("#1234", Some("https://tracker.example.com/issue/1234")),
+            // nothing after the prefix
+            ("gh:", None),
+            ("#", None),
+            ("example.com", None),
+        ];
+        for (query, url) in table {
+            assert_eq!(
+                expand_shorthand(query, &shorthands).as_deref(),
+                url,
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn file_names_are_not_urls() {
+        for query in ["main.rs", "README.md", "setup.py", "install.sh", "Cargo.lock"] {
+            assert!(!is_url(query), "{}", query);
+        }
+        // unless typed like a URL
+        for query in ["https://main.rs", "www.setup.py", "install.sh/", "main.rs:8000"] {
+            assert!(is_url(query), "{}", query);
+        }
+        for query in ["example.com", "example.co.uk/path", "localhost:8000", "10.0.0.1:8080"] {
+            assert!(is_url(query), "{}", query);
+        }
+    }
+}
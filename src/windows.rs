@@ -0,0 +1,331 @@
+// Running apps and their windows. Apps are told by the stem of their path,
+// `Safari` for `/Applications/Safari.app` or `firefox` for
+// `firefox.desktop`, compared without case to what the window manager
+// reports
+use crate::procs;
+use crate::shell::in_path;
+use serde_json::Value as Json;
+use std::{
+    collections::HashSet,
+    env, io,
+    path::Path,
+    process::{Command, Stdio},
+    sync::Mutex,
+};
+
+pub trait WindowManager {
+    // lowercase names of the running apps
+    fn running(&self) -> io::Result<HashSet<String>>;
+    fn focus(&self, app: &str) -> io::Result<()>;
+    fn quit(&self, app: &str) -> io::Result<()>;
+    fn new_window(&self, app: &str) -> io::Result<()>;
+}
+
+pub struct MacOs;
+pub struct Wmctrl;
+// sway and i3 share their IPC, through `swaymsg` or `i3-msg`
+pub struct Ipc(&'static str);
+// what runs, from the process list. Cannot tell windows apart
+pub struct Processes;
+// nothing but the given apps run, and actions do nothing. For tests, and
+// `window_manager = "none"`
+pub struct Stub(pub Vec<String>);
+
+// `kind` as in `Config::window_manager`
+pub fn detect(kind: &str) -> Box<dyn WindowManager> {
+    match kind {
+        "macos" => Box::new(MacOs),
+        "wmctrl" => Box::new(Wmctrl),
+        "sway" => Box::new(Ipc("swaymsg")),
+        "i3" => Box::new(Ipc("i3-msg")),
+        "processes" => Box::new(Processes),
+        "none" => Box::new(Stub(vec![])),
+        _ if cfg!(target_os = "macos") => Box::new(MacOs),
+        _ if env::var_os("SWAYSOCK").is_some() && in_path("swaymsg") => Box::new(Ipc("swaymsg")),
+        _ if env::var_os("I3SOCK").is_some() && in_path("i3-msg") => Box::new(Ipc("i3-msg")),
+        _ if env::var_os("DISPLAY").is_some() && in_path("wmctrl") => Box::new(Wmctrl),
+        _ => Box::new(Processes),
+    }
+}
+
+lazy_static! {
+    // what `refresh` found last, and for which `kind`
+    static ref RUNNING: Mutex<Option<(String, HashSet<String>)>> = Mutex::new(None);
+}
+
+// Ask the window manager which apps run. Slow, so it is called from a
+// thread of its own, never while searching
+pub fn refresh(kind: &str) {
+    let apps = detect(kind).running().unwrap_or_default();
+    *RUNNING.lock().unwrap() = Some((kind.to_string(), apps));
+}
+
+// Running apps as of the last `refresh` with `kind`, none before. Does not
+// wait for the window manager
+pub fn running(kind: &str) -> HashSet<String> {
+    match &*RUNNING.lock().unwrap() {
+        Some((k, apps)) if k == kind => apps.clone(),
+        _ => HashSet::new(),
+    }
+}
+
+pub fn app_name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+impl WindowManager for MacOs {
+    fn running(&self) -> io::Result<HashSet<String>> {
+        let names = output(Command::new("osascript").arg("-e").arg(
+            "tell application \"System Events\" to get name of every application process whose background only is false",
+        ))?;
+        Ok(names.trim().split(", ").map(|s| s.to_lowercase()).collect())
+    }
+
+    fn focus(&self, app: &str) -> io::Result<()> {
+        run(Command::new("open").arg("-a").arg(app))
+    }
+
+    fn quit(&self, app: &str) -> io::Result<()> {
+        osascript(&format!("tell application \"{}\" to quit", app))
+    }
+
+    // what ⌘N does in most apps
+    fn new_window(&self, app: &str) -> io::Result<()> {
+        osascript(&format!("tell application \"{}\" to activate", app))?;
+        osascript("tell application \"System Events\" to keystroke \"n\" using command down")
+    }
+}
+
+impl WindowManager for Wmctrl {
+    fn running(&self) -> io::Result<HashSet<String>> {
+        Ok(self.windows()?.into_iter().flat_map(|(_, names)| names).collect())
+    }
+
+    fn focus(&self, app: &str) -> io::Result<()> {
+        match self.windows_of(app)?.first() {
+            Some(id) => run(Command::new("wmctrl").arg("-i").arg("-a").arg(id)),
+            None => launch(app),
+        }
+    }
+
+    fn quit(&self, app: &str) -> io::Result<()> {
+        for id in self.windows_of(app)? {
+            run(Command::new("wmctrl").arg("-i").arg("-c").arg(id))?;
+        }
+        Ok(())
+    }
+
+    fn new_window(&self, app: &str) -> io::Result<()> {
+        launch(app)
+    }
+}
+
+impl Wmctrl {
+    // window ids with the lowercase parts of their `WM_CLASS`: `instance`,
+    // `class` and `instance.class`
+    fn windows(&self) -> io::Result<Vec<(String, Vec<String>)>> {
+        Ok(output(Command::new("wmctrl").arg("-lx"))?
+            .lines()
+            .filter_map(|line| {
+                let mut columns = line.split_whitespace();
+                let id = columns.next()?.to_string();
+                let class = columns.nth(1)?.to_lowercase();
+                let mut names: Vec<String> = class.split('.').map(str::to_string).collect();
+                names.push(class);
+                Some((id, names))
+            })
+            .collect())
+    }
+
+    fn windows_of(&self, app: &str) -> io::Result<Vec<String>> {
+        let name = app_name(app);
+        Ok(self
+            .windows()?
+            .into_iter()
+            .filter(|(_, names)| names.contains(&name))
+            .map(|(id, _)| id)
+            .collect())
+    }
+}
+
+impl WindowManager for Ipc {
+    fn running(&self) -> io::Result<HashSet<String>> {
+        Ok(self.windows()?.into_iter().map(|(_, name)| name).collect())
+    }
+
+    fn focus(&self, app: &str) -> io::Result<()> {
+        match self.windows_of(app)?.first() {
+            Some(id) => self.command(&format!("[con_id={}] focus", id)),
+            None => launch(app),
+        }
+    }
+
+    fn quit(&self, app: &str) -> io::Result<()> {
+        for id in self.windows_of(app)? {
+            self.command(&format!("[con_id={}] kill", id))?;
+        }
+        Ok(())
+    }
+
+    fn new_window(&self, app: &str) -> io::Result<()> {
+        launch(app)
+    }
+}
+
+impl Ipc {
+    // container ids with the lowercase `app_id`, or X11 class under
+    // XWayland and i3
+    fn windows(&self) -> io::Result<Vec<(u64, String)>> {
+        fn walk(node: &Json, out: &mut Vec<(u64, String)>) {
+            let name = node["app_id"]
+                .as_str()
+                .or_else(|| node["window_properties"]["class"].as_str());
+            if let (Some(id), Some(name)) = (node["id"].as_u64(), name) {
+                out.push((id, name.to_lowercase()));
+            }
+            for key in ["nodes", "floating_nodes"] {
+                if let Some(children) = node[key].as_array() {
+                    children.iter().for_each(|child| walk(child, out));
+                }
+            }
+        }
+
+        let tree: Json = serde_json::from_str(&output(
+            Command::new(self.0).arg("-t").arg("get_tree"),
+        )?)?;
+        let mut windows = vec![];
+        walk(&tree, &mut windows);
+        Ok(windows)
+    }
+
+    fn windows_of(&self, app: &str) -> io::Result<Vec<u64>> {
+        let name = app_name(app);
+        Ok(self
+            .windows()?
+            .into_iter()
+            .filter(|(_, n)| *n == name)
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    fn command(&self, command: &str) -> io::Result<()> {
+        run(Command::new(self.0).arg(command))
+    }
+}
+
+impl WindowManager for Processes {
+    fn running(&self) -> io::Result<HashSet<String>> {
+        Ok(procs::source()
+            .list()?
+            .into_iter()
+            .map(|p| p.name.to_lowercase())
+            .collect())
+    }
+
+    // launching a running app brings it forward, or opens a window
+    fn focus(&self, app: &str) -> io::Result<()> {
+        launch(app)
+    }
+
+    fn quit(&self, app: &str) -> io::Result<()> {
+        let name = app_name(app);
+        for process in procs::source().list()? {
+            if process.name.to_lowercase() == name {
+                procs::kill(process.pid, "TERM").map_err(|e| io::Error::other(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn new_window(&self, app: &str) -> io::Result<()> {
+        launch(app)
+    }
+}
+
+impl WindowManager for Stub {
+    fn running(&self) -> io::Result<HashSet<String>> {
+        Ok(self.0.iter().map(|s| s.to_lowercase()).collect())
+    }
+
+    fn focus(&self, _app: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn quit(&self, _app: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn new_window(&self, _app: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// `gtk-launch` for desktop entries, `open` on macOS, else the path itself
+fn launch(app: &str) -> io::Result<()> {
+    let mut command = if app.ends_with(".desktop") {
+        let mut command = Command::new("gtk-launch");
+        command.arg(Path::new(app).file_name().unwrap_or_default());
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg(app);
+        command
+    } else {
+        Command::new(app)
+    };
+    command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+fn osascript(script: &str) -> io::Result<()> {
+    run(Command::new("osascript").arg("-e").arg(script))
+}
+
+fn run(command: &mut Command) -> io::Result<()> {
+    output(command).map(|_| ())
+}
+
+// stdout of a command that has to succeed
+fn output(command: &mut Command) -> io::Result<String> {
+    let output = command.stderr(Stdio::null()).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("{:?} failed", command)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn app_names() {
+        assert_eq!(app_name("/Applications/Safari.app"), "safari");
+        assert_eq!(app_name("/usr/share/applications/firefox.desktop"), "firefox");
+        assert_eq!(
+            app_name("/usr/share/applications/org.gnome.Nautilus.desktop"),
+            "org.gnome.nautilus"
+        );
+    }
+
+    #[test]
+    fn running_reads_the_last_refresh() {
+        *RUNNING.lock().unwrap() = Some(("wmctrl".to_string(), HashSet::from(["firefox".into()])));
+        assert!(running("wmctrl").contains("firefox"));
+        // another window manager is not asked meanwhile
+        assert!(running("sway").is_empty());
+    }
+
+    #[test]
+    fn stub_matches_app_names() {
+        let running = Stub(vec!["Firefox".to_string()]).running().unwrap();
+        assert!(running.contains(&app_name("/usr/share/applications/firefox.desktop")));
+        assert!(!running.contains(&app_name("/Applications/Safari.app")));
+    }
+}